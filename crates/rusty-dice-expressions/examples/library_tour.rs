@@ -0,0 +1,50 @@
+//! A tour of using `rusty-dice` and `rusty-dice-expressions` as libraries
+//!
+//! Run it with `cargo run -p rusty-dice-expressions --example library_tour`
+use rand::{SeedableRng, rngs::StdRng};
+use rusty_dice::{Advantage, Dice, PoolResult};
+use rusty_dice_expressions::{Eval, ExprKind, ExpressionError, RollRegistry};
+
+fn main() -> Result<(), ExpressionError> {
+    // A seeded generator makes every run print the same results
+    let mut rng = StdRng::seed_from_u64(2024);
+
+    // Plain dice from the core crate
+    let fireball = Dice::new(8, 6);
+    let rolls = fireball.roll_with(&mut rng);
+    let total = rusty_dice::total(std::slice::from_ref(&rolls));
+    println!("{fireball}: {rolls:?} = {total}");
+    println!("  most likely total: {:?}", fireball.mode());
+    println!(
+        "  4d6 drop lowest averages {:.2}",
+        Dice::new(4, 6).expected_keep_highest(3)
+    );
+
+    let check = Dice::d20_check(&mut rng, Advantage::Advantage);
+    println!("d20 with advantage: {check}");
+
+    let pool = PoolResult::new(&Dice::new(6, 10).roll_with(&mut rng), 8, 1);
+    println!("6d10 pool: {} net successes", pool.net);
+
+    // Expressions, with labels and references to earlier results
+    let expr = "atk: 1d20 + 5; dmg: 2d6 + 3; total: atk + dmg".parse::<ExprKind>()?;
+    let res = expr.clone().eval_with(&mut rng)?;
+    println!("{expr} => {res}");
+
+    // Every die that goes into an expression
+    for die in "2d6 + 1d8".parse::<ExprKind>()?.roll_tray(&mut rng)? {
+        println!("  {} rolled a {} (d{})", die.source, die.face, die.sides);
+    }
+
+    // Named rolls, looked up later
+    let mut registry = RollRegistry::new();
+    registry.register_labeled(&"heal: 2d4 + 2; sneak attack: 3d6".parse()?);
+    println!("{}", registry.eval_named("heal")?);
+
+    Ok(())
+}
+
+#[test]
+fn runs() {
+    main().unwrap();
+}
@@ -1,8 +1,14 @@
-use std::str::FromStr;
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+
+use rand::Rng;
+use rusty_dice::Dice;
 
 use crate::{
     ExpressionError,
-    parse::{Atom, Expr, ExprKind, Operation, parse_expr, parse_expr_kind},
+    parse::{Atom, Expr, ExprKind, Function, Operation, ParseRes, parse_expr, parse_expr_kind},
 };
 
 /// Trait for objects that support evaluation
@@ -15,6 +21,16 @@ pub trait Eval {
     /// Returns a new instance of the evaluated type,
     /// with all inner calculations reduced as much as possible
     fn eval(self) -> Result<Self, ExpressionError>
+    where
+        Self: Sized,
+    {
+        self.eval_with(&mut rand::rng())
+    }
+
+    /// Perform the evaluation, rolling all dice with the provided generator
+    ///
+    /// Passing the same seeded generator makes the evaluation reproducible
+    fn eval_with<R: Rng + ?Sized>(self, rng: &mut R) -> Result<Self, ExpressionError>
     where
         Self: Sized;
 
@@ -25,33 +41,217 @@ pub trait Eval {
     fn eval_complete(&self) -> bool;
 }
 
-impl Eval for Expr {
-    fn eval(self) -> Result<Self, ExpressionError> {
+/// A single physical die rolled while evaluating an expression
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DieResult {
+    /// The dice the die belongs to, e.g. `2d6`
+    pub source: String,
+
+    /// The number of sides of the die
+    pub sides: u32,
+
+    /// The value the die shows. Only custom dice can show zero or negative values
+    pub face: i32,
+}
+
+/// Evaluate an expression that is expected to produce a single number
+fn eval_num<R: Rng + ?Sized>(
+    expr: Expr,
+    rng: &mut R,
+    tray: Option<&mut Vec<DieResult>>,
+) -> Result<i32, ExpressionError> {
+    let res = expr.eval_into(rng, tray)?;
+    res.get_num()
+        .ok_or_else(|| ExpressionError::EvaluationError {
+            expr: res.to_string(),
+            reason: "expected a number".to_string(),
+        })
+}
+
+/// The most dice a single dice notation can roll during evaluation
+///
+/// Every die is rolled on its own, so larger rolls would take very long to evaluate.
+/// Rolling more dice fails with [`ExpressionError::Overflow`]
+pub const MAX_DICE: u32 = 1_000_000;
+
+/// Roll dice one by one, combining their faces into a total and putting every die
+/// onto the tray if there is one
+///
+/// The dice are never stored without a tray, so plain evaluation doesn't pay for it.
+/// Returns `None` if the total doesn't fit into an `i32`
+fn total_dice(
+    atom: &Atom,
+    sides: u32,
+    faces: impl Iterator<Item = i64>,
+    combine: impl Fn(i32, i32) -> Option<i32>,
+    mut tray: Option<&mut Vec<DieResult>>,
+) -> Option<i32> {
+    let source = tray.is_some().then(|| atom.to_string());
+    let mut total = 0;
+    for face in faces {
+        let face = i32::try_from(face).ok()?;
+        total = combine(total, face)?;
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(source = %atom, sides, face, "rolled a die");
+
+        if let (Some(tray), Some(source)) = (tray.as_deref_mut(), &source) {
+            tray.push(DieResult {
+                source: source.clone(),
+                sides,
+                face,
+            });
+        }
+    }
+
+    Some(total)
+}
+
+/// Roll the dice of an atom, putting every die onto the tray if there is one
+///
+/// Returns the total of the dice, or `None` if the atom is not a dice atom
+fn roll_atom<R: Rng + ?Sized>(
+    atom: &Atom,
+    rng: &mut R,
+    tray: Option<&mut Vec<DieResult>>,
+) -> Result<Option<i32>, ExpressionError> {
+    let quantity = match atom {
+        Atom::Dice(dice) | Atom::ConcatDice(dice) => dice.quantity,
+        Atom::CustomDice(dice) => dice.quantity,
+        _ => 0,
+    };
+    if quantity > MAX_DICE {
+        return Err(ExpressionError::Overflow(atom.to_string()));
+    }
+
+    let res = match atom {
+        // If the expression is a dice roll -- sum up the results
+        Atom::Dice(die) => {
+            if die.quantity > 0 && die.num_sides == 0 {
+                return Err(ExpressionError::EvaluationError {
+                    expr: die.to_string(),
+                    reason: "a die needs at least one side".to_string(),
+                });
+            }
+
+            let faces = die.roll_iter_with(rng).map(i64::from);
+            total_dice(atom, die.num_sides, faces, i32::checked_add, tray)
+                .ok_or_else(|| ExpressionError::Overflow(atom.to_string()))?
+        }
+
+        Atom::CustomDice(dice) => {
+            if dice.faces.is_empty() {
+                return Err(ExpressionError::EvaluationError {
+                    expr: dice.to_string(),
+                    reason: "a die needs at least one face".to_string(),
+                });
+            }
+
+            let sides = u32::try_from(dice.faces.len()).unwrap_or(u32::MAX);
+            let faces = dice.roll_iter_with(rng).map(i64::from);
+            total_dice(atom, sides, faces, i32::checked_add, tray)
+                .ok_or_else(|| ExpressionError::Overflow(dice.to_string()))?
+        }
+
+        Atom::ConcatDice(dice) => {
+            let faces = dice.roll_iter_with(rng).map(i64::from);
+            let concat = |acc: i32, digit| acc.checked_mul(10)?.checked_add(digit);
+            total_dice(atom, dice.num_sides, faces, concat, tray)
+                .ok_or_else(|| ExpressionError::Overflow(atom.to_string()))?
+        }
+
+        Atom::Reference(name) => return Err(ExpressionError::UnknownRoll(name.clone())),
+
+        Atom::Number(_) | Atom::Operation(_) => return Ok(None),
+    };
+
+    Ok(Some(res))
+}
+
+impl Expr {
+    /// Evaluate the expression, putting every die that gets rolled onto the tray if there is one
+    fn eval_into<R: Rng + ?Sized>(
+        self,
+        rng: &mut R,
+        mut tray: Option<&mut Vec<DieResult>>,
+    ) -> Result<Self, ExpressionError> {
         match self {
-            // If the expression is a dice roll -- sum up the results
-            Expr::Constant(Atom::Dice(die)) => {
-                let res: u32 = die.roll().iter().sum();
-                Ok(Expr::Constant(Atom::Number(res as i32)))
-            }
-
-            Expr::Application(expr, (l, r)) => {
-                let l = l
-                    .eval()?
-                    .get_num()
-                    .ok_or(ExpressionError::EvaluationError)?;
-                let r = r
-                    .eval()?
-                    .get_num()
-                    .ok_or(ExpressionError::EvaluationError)?;
-
-                match expr {
-                    Operation::Add => Ok(Expr::Constant(Atom::Number(l + r))),
-                    Operation::Sub => Ok(Expr::Constant(Atom::Number(l - r))),
+            Expr::Constant(atom) => match roll_atom(&atom, rng, tray)? {
+                Some(res) => Ok(Expr::Constant(Atom::Number(res))),
+                None => Ok(Expr::Constant(atom)),
+            },
+
+            Expr::Application(op, (l, r)) => {
+                let repr = Expr::Application(op, (l.clone(), r.clone())).to_string();
+                let l = eval_num(*l, rng, tray.as_deref_mut())?;
+                let r = eval_num(*r, rng, tray)?;
+
+                let res = match op {
+                    Operation::Add => l.checked_add(r),
+                    Operation::Sub => l.checked_sub(r),
+                };
+                let res = res.ok_or(ExpressionError::Overflow(repr))?;
+
+                #[cfg(feature = "tracing")]
+                tracing::trace!(operation = %op, l, r, result = res, "applied an operation");
+
+                Ok(Expr::Constant(Atom::Number(res)))
+            }
+            Expr::Function(func, args) => {
+                if args.is_empty() {
+                    return Err(ExpressionError::EvaluationError {
+                        expr: Expr::Function(func, args).to_string(),
+                        reason: "a function needs at least one argument".to_string(),
+                    });
                 }
+
+                let mut values = vec![];
+                for arg in args {
+                    values.push(eval_num(arg, rng, tray.as_deref_mut())?);
+                }
+
+                let res = match func {
+                    Function::Min => values.iter().min(),
+                    Function::Max => values.iter().max(),
+                };
+                let res = *res.expect("There should always be at least one argument");
+
+                #[cfg(feature = "tracing")]
+                tracing::trace!(function = %func, ?values, result = res, "applied a function");
+
+                Ok(Expr::Constant(Atom::Number(res)))
+            }
+            Expr::DynamicDice(quantity, num_sides) => {
+                let repr = Expr::DynamicDice(quantity.clone(), num_sides.clone()).to_string();
+                let quantity = eval_num(*quantity, rng, tray.as_deref_mut())?;
+                let num_sides = eval_num(*num_sides, rng, tray.as_deref_mut())?;
+
+                let to_err = |reason: &str| ExpressionError::EvaluationError {
+                    expr: repr.clone(),
+                    reason: reason.to_string(),
+                };
+
+                let quantity = u32::try_from(quantity)
+                    .map_err(|_| to_err("the number of dice can't be negative"))?;
+                let num_sides = u32::try_from(num_sides).unwrap_or_default();
+                if quantity > 0 && num_sides == 0 {
+                    return Err(to_err("a die needs at least one side"));
+                }
+
+                Expr::Constant(Atom::Dice(Dice::new(quantity, num_sides))).eval_into(rng, tray)
             }
-            Expr::Constant(_) => Ok(self),
         }
     }
+}
+
+impl Eval for Expr {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(expr = %self))
+    )]
+    fn eval_with<R: Rng + ?Sized>(self, rng: &mut R) -> Result<Self, ExpressionError> {
+        self.eval_into(rng, None)
+    }
 
     fn eval_complete(&self) -> bool {
         match self {
@@ -61,18 +261,146 @@ impl Eval for Expr {
     }
 }
 
+impl Expr {
+    /// The smallest value the expression can possibly evaluate to
+    ///
+    /// Computed without rolling anything, so it is cheap to call.
+    /// Expressions without bounds, like ones that reference a labeled result,
+    /// could evaluate to anything and so give [`i32::MIN`]; see [`Expr::try_bounds`]
+    pub fn min_value(&self) -> i32 {
+        self.try_bounds().map_or(i32::MIN, |(min, _)| min)
+    }
+
+    /// The largest value the expression can possibly evaluate to
+    ///
+    /// The same rules as for [`Expr::min_value`] apply,
+    /// so expressions without bounds give [`i32::MAX`]
+    pub fn max_value(&self) -> i32 {
+        self.try_bounds().map_or(i32::MAX, |(_, max)| max)
+    }
+
+    /// Replace the references to labeled results with their values
+    ///
+    /// `values` maps labels to the totals they rolled.
+    /// References that aren't in it are left as they are
+    pub fn resolve(self, values: &HashMap<String, i32>) -> Expr {
+        match self {
+            Expr::Constant(Atom::Reference(name)) => match values.get(&name) {
+                Some(&value) => Expr::Constant(Atom::Number(value)),
+                None => Expr::Constant(Atom::Reference(name)),
+            },
+            Expr::Constant(_) => self,
+            Expr::Application(op, (l, r)) => Expr::Application(
+                op,
+                (Box::new(l.resolve(values)), Box::new(r.resolve(values))),
+            ),
+            Expr::Function(func, args) => {
+                Expr::Function(func, args.into_iter().map(|a| a.resolve(values)).collect())
+            }
+            Expr::DynamicDice(quantity, num_sides) => Expr::DynamicDice(
+                Box::new(quantity.resolve(values)),
+                Box::new(num_sides.resolve(values)),
+            ),
+        }
+    }
+
+    /// The smallest and largest values the expression can possibly evaluate to
+    ///
+    /// Returns [`None`] for expressions that can't be evaluated on their own,
+    /// like ones that reference a labeled result or roll dice without sides.
+    /// [`Expr::resolve`] the references first to get their bounds
+    pub fn try_bounds(&self) -> Option<(i32, i32)> {
+        let res = match self {
+            // Rolling dice without sides fails, so there is nothing to bound
+            Expr::Constant(Atom::Dice(die)) if die.quantity > 0 && die.num_sides == 0 => {
+                return None;
+            }
+            Expr::Constant(Atom::CustomDice(dice))
+                if dice.quantity > 0 && dice.faces.is_empty() =>
+            {
+                return None;
+            }
+            Expr::Constant(Atom::Dice(die)) => {
+                let quantity = i32::try_from(die.quantity).unwrap_or(i32::MAX);
+                let num_sides = i32::try_from(die.num_sides).unwrap_or(i32::MAX);
+                (quantity, quantity.saturating_mul(num_sides))
+            }
+            Expr::Constant(Atom::CustomDice(dice)) => {
+                let quantity = i32::try_from(dice.quantity).unwrap_or(i32::MAX);
+                let min_face = dice.faces.iter().copied().min().unwrap_or_default();
+                let max_face = dice.faces.iter().copied().max().unwrap_or_default();
+                (
+                    quantity.saturating_mul(min_face),
+                    quantity.saturating_mul(max_face),
+                )
+            }
+            Expr::Constant(Atom::ConcatDice(dice)) => {
+                let repeat = |digit: u32| {
+                    (0..dice.quantity).fold(0i32, |acc, _| {
+                        acc.saturating_mul(10).saturating_add_unsigned(digit)
+                    })
+                };
+                (repeat(1), repeat(dice.num_sides))
+            }
+            Expr::Constant(Atom::Number(n)) => (*n, *n),
+            // A reference is only known once the expression it refers to is rolled
+            Expr::Constant(Atom::Operation(_) | Atom::Reference(_)) => return None,
+            Expr::Application(op, (l, r)) => {
+                let (l_min, l_max) = l.try_bounds()?;
+                let (r_min, r_max) = r.try_bounds()?;
+                match op {
+                    Operation::Add => (l_min.saturating_add(r_min), l_max.saturating_add(r_max)),
+                    // Subtracting flips the bounds of the right operand
+                    Operation::Sub => (l_min.saturating_sub(r_max), l_max.saturating_sub(r_min)),
+                }
+            }
+            Expr::Function(func, args) => {
+                let bounds = args
+                    .iter()
+                    .map(Expr::try_bounds)
+                    .collect::<Option<Vec<_>>>()?;
+                let pick = match func {
+                    Function::Min => i32::min,
+                    Function::Max => i32::max,
+                };
+                // A function without arguments can't be evaluated
+                bounds
+                    .into_iter()
+                    .reduce(|(a_min, a_max), (b_min, b_max)| {
+                        (pick(a_min, b_min), pick(a_max, b_max))
+                    })?
+            }
+            Expr::DynamicDice(quantity, num_sides) => {
+                let (q_min, q_max) = quantity.try_bounds()?;
+                let (_, s_max) = num_sides.try_bounds()?;
+                // A negative number of dice fails, and so do dice that always end up without sides,
+                // unless no dice are rolled at all
+                if q_max < 0 {
+                    return None;
+                }
+                if s_max <= 0 {
+                    return (q_min <= 0).then_some((0, 0));
+                }
+                // Every die shows at least a 1, so the fewest dice give the lowest total
+                (q_min.max(0), q_max.saturating_mul(s_max))
+            }
+        };
+
+        Some(res)
+    }
+}
+
 impl Eval for ExprKind {
-    fn eval(self) -> Result<ExprKind, ExpressionError>
-    where
-        Self: Sized,
-    {
+    fn eval_with<R: Rng + ?Sized>(self, rng: &mut R) -> Result<ExprKind, ExpressionError> {
         match self {
-            ExprKind::Simple(expr) => Ok(ExprKind::Simple(expr.eval()?)),
-            ExprKind::Labeled(l, expr) => Ok(ExprKind::Labeled(l, expr.eval()?)),
+            ExprKind::Simple(expr) => Ok(ExprKind::Simple(expr.eval_with(rng)?)),
+            ExprKind::Labeled(l, expr) => Ok(ExprKind::Labeled(l, expr.eval_with(rng)?)),
             ExprKind::Separated(expr_kinds) => {
+                let mut values = HashMap::new();
                 let mut new_kinds = vec![];
                 for kind in expr_kinds {
-                    let kind = kind.eval()?;
+                    let kind = kind.resolve(&values).eval_with(rng)?;
+                    kind.record_value(&mut values);
                     new_kinds.push(kind);
                 }
 
@@ -90,13 +418,161 @@ impl Eval for ExprKind {
     }
 }
 
+impl ExprKind {
+    /// Replace the references to labeled results with their values
+    ///
+    /// See [`Expr::resolve`]
+    pub fn resolve(self, values: &HashMap<String, i32>) -> ExprKind {
+        match self {
+            ExprKind::Simple(expr) => ExprKind::Simple(expr.resolve(values)),
+            ExprKind::Labeled(l, expr) => ExprKind::Labeled(l, expr.resolve(values)),
+            ExprKind::Separated(expr_kinds) => {
+                ExprKind::Separated(expr_kinds.into_iter().map(|k| k.resolve(values)).collect())
+            }
+        }
+    }
+
+    /// Remember the value of an evaluated labeled expression, so later ones can reference it
+    ///
+    /// Together with [`ExprKind::resolve`] this allows evaluating the parts of
+    /// a separated expression one by one, the same way [`Eval::eval_with`] does
+    pub fn record_value(&self, values: &mut HashMap<String, i32>) {
+        if let ExprKind::Labeled(l, expr) = self
+            && let Some(value) = expr.get_num()
+        {
+            values.insert(l.trim().to_string(), value);
+        }
+    }
+
+    /// The numeric total of an evaluated expression
+    ///
+    /// Separated expressions are totalled by summing up all of their parts
+    fn total(&self) -> Result<i32, ExpressionError> {
+        match self {
+            ExprKind::Simple(expr) | ExprKind::Labeled(_, expr) => {
+                expr.get_num()
+                    .ok_or_else(|| ExpressionError::EvaluationError {
+                        expr: expr.to_string(),
+                        reason: "expected a number".to_string(),
+                    })
+            }
+            ExprKind::Separated(expr_kinds) => expr_kinds.iter().try_fold(0i32, |acc, kind| {
+                acc.checked_add(kind.total()?)
+                    .ok_or_else(|| ExpressionError::Overflow(self.to_string()))
+            }),
+        }
+    }
+
+    /// Estimate the distribution of the expression's total by evaluating it many times
+    ///
+    /// Returns a map from every total that came up to the number of times it did.
+    /// Since the expression is actually rolled, this works for any expression,
+    /// at the cost of only being an approximation
+    ///
+    /// For separated expressions the total is the sum of all the sub-expressions
+    pub fn sample_distribution<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        samples: u32,
+    ) -> Result<BTreeMap<i32, u32>, ExpressionError> {
+        let mut buckets = BTreeMap::new();
+        for _ in 0..samples {
+            *buckets.entry(self.roll_total(rng)?).or_insert(0) += 1;
+        }
+
+        Ok(buckets)
+    }
+
+    /// Roll the expression and list every die that was rolled, in order
+    ///
+    /// Each die is tagged with the dice it came from and the face it shows.
+    /// Dice that decide the quantity or sides of other dice, like the `1d4` in `(1d4)d6`,
+    /// are listed as well
+    pub fn roll_tray<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> Result<Vec<DieResult>, ExpressionError> {
+        let mut values = HashMap::new();
+        let mut tray = vec![];
+        for kind in self.iter() {
+            let res = match kind.clone().resolve(&values) {
+                ExprKind::Simple(expr) => ExprKind::Simple(expr.eval_into(rng, Some(&mut tray))?),
+                ExprKind::Labeled(l, expr) => {
+                    ExprKind::Labeled(l, expr.eval_into(rng, Some(&mut tray))?)
+                }
+                ExprKind::Separated(_) => continue,
+            };
+            res.record_value(&mut values);
+        }
+
+        Ok(tray)
+    }
+
+    /// Evaluate the expression `n` times and keep the highest total
+    ///
+    /// The expression is always evaluated at least once, even if `n` is zero.
+    /// For separated expressions the total is the sum of all the sub-expressions
+    pub fn best_of<R: Rng + ?Sized>(&self, rng: &mut R, n: u32) -> Result<i32, ExpressionError> {
+        self.extreme_of(rng, n, i32::max)
+    }
+
+    /// Evaluate the expression `n` times and keep the lowest total
+    ///
+    /// The same rules as for [`ExprKind::best_of`] apply
+    pub fn worst_of<R: Rng + ?Sized>(&self, rng: &mut R, n: u32) -> Result<i32, ExpressionError> {
+        self.extreme_of(rng, n, i32::min)
+    }
+
+    fn extreme_of<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        n: u32,
+        pick: fn(i32, i32) -> i32,
+    ) -> Result<i32, ExpressionError> {
+        let mut res = self.roll_total(rng)?;
+        for _ in 1..n {
+            res = pick(res, self.roll_total(rng)?);
+        }
+
+        Ok(res)
+    }
+
+    /// Evaluate the expression once and get its total
+    fn roll_total<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<i32, ExpressionError> {
+        self.clone().eval_with(rng)?.total()
+    }
+}
+
+/// Run a parser over the whole input, pointing at the first part that couldn't be parsed
+fn parse_complete<'a, T>(
+    s: &'a str,
+    parser: impl FnOnce(&'a str) -> ParseRes<'a, T>,
+) -> Result<T, ExpressionError> {
+    let rest = match parser(s) {
+        Ok((rest, res)) if rest.trim().is_empty() => return Ok(res),
+        Ok((rest, _)) => rest,
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => match e.reason {
+            Some(reason) => return Err(ExpressionError::ParseError(reason)),
+            None => e.input,
+        },
+        Err(e) => return Err(ExpressionError::ParseError(e.to_string())),
+    };
+
+    Err(ExpressionError::ParseError(format!(
+        "unexpected input `{}`",
+        rest.trim()
+    )))
+}
+
 impl FromStr for ExprKind {
     type Err = ExpressionError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        parse_expr_kind(s)
-            .map(|(_, exp)| exp)
-            .map_err(|e| ExpressionError::ParseError(e.to_string()))
+        if s.trim().is_empty() {
+            return Err(ExpressionError::EmptyExpression);
+        }
+
+        parse_complete(s, parse_expr_kind)
     }
 }
 
@@ -104,15 +580,18 @@ impl FromStr for Expr {
     type Err = ExpressionError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        parse_expr(s)
-            .map(|(_, exp)| exp)
-            .map_err(|e| ExpressionError::ParseError(e.to_string()))
+        if s.trim().is_empty() {
+            return Err(ExpressionError::EmptyExpression);
+        }
+
+        parse_complete(s, parse_expr)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
 
     fn eval_from_str(src: &str) -> Result<ExprKind, ExpressionError> {
         let expr = src.parse::<ExprKind>()?;
@@ -133,10 +612,400 @@ mod tests {
         assert!(res.eval_complete(), "res = {:?}", res);
     }
 
+    #[test]
+    fn test_left_grouping() {
+        let res = "10 - 2 - 3".parse::<Expr>().unwrap().eval().unwrap();
+        assert_eq!(res.get_num(), Some(5));
+    }
+
     #[test]
     fn test_separation() {
         let expr = "1d4 + 4; 2d6; my roll: 1d4 + 3";
         let res = eval_from_str(expr).unwrap();
         assert!(res.eval_complete())
     }
+
+    #[test]
+    fn test_labels_survive_eval() {
+        let parsed = "attack: 1d20 + 5".parse::<ExprKind>().unwrap();
+        let res = parsed.clone().eval().unwrap();
+
+        let pair = (parsed.iter().next(), res.iter().next());
+        let (Some(ExprKind::Labeled(before, formula)), Some(ExprKind::Labeled(after, total))) =
+            pair
+        else {
+            panic!("expected labeled expressions, got {parsed:?} and {res:?}");
+        };
+        assert_eq!(before, after);
+        assert_eq!(formula.to_string(), "1d20 + 5");
+        assert!((6..=25).contains(&total.get_num().unwrap()));
+    }
+
+    #[test]
+    fn test_sample_distribution() {
+        let expr = "2d6 + 1".parse::<ExprKind>().unwrap();
+        let dist = expr
+            .sample_distribution(&mut StdRng::seed_from_u64(7), 10_000)
+            .unwrap();
+
+        assert_eq!(dist.values().sum::<u32>(), 10_000);
+        assert!(dist.keys().all(|total| (3..=13).contains(total)));
+        assert!(dist[&8] > dist[&3]);
+    }
+
+    #[test]
+    fn test_best_of() {
+        let simple = "1d20 + 2".parse::<Expr>().unwrap();
+        let expr = ExprKind::Simple(simple.clone());
+        for seed in 0..100 {
+            let best = expr.best_of(&mut StdRng::seed_from_u64(seed), 3).unwrap();
+            let worst = expr.worst_of(&mut StdRng::seed_from_u64(seed), 3).unwrap();
+            let (min, max) = (simple.min_value(), simple.max_value());
+            assert!(best >= min && best <= max);
+            assert!(worst >= min && worst <= best);
+        }
+
+        let expr = "3; 4".parse::<ExprKind>().unwrap();
+        assert_eq!(expr.best_of(&mut StdRng::seed_from_u64(0), 0), Ok(7));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let res = eval_from_str("2000000000 + 2000000000");
+        assert_eq!(
+            res,
+            Err(ExpressionError::Overflow(
+                "2000000000 + 2000000000".to_string()
+            ))
+        );
+
+        let res = eval_from_str("-2000000000 - 2000000000");
+        assert!(matches!(res, Err(ExpressionError::Overflow(_))));
+
+        let res = eval_from_str("1d2147483647").unwrap();
+        assert!(res.eval_complete());
+
+        assert!(matches!(
+            eval_from_str("2d0"),
+            Err(ExpressionError::EvaluationError { .. })
+        ));
+
+        let res = eval_from_str("1000d4294967295");
+        assert_eq!(
+            res,
+            Err(ExpressionError::Overflow("1000d4294967295".to_string()))
+        );
+
+        // Rolled one by one without being stored, but there is a limit to how many
+        let res = eval_from_str("(2000000000)d2");
+        assert_eq!(
+            res,
+            Err(ExpressionError::Overflow("2000000000d2".to_string()))
+        );
+        let res = eval_from_str(&format!("{MAX_DICE}d1")).unwrap();
+        assert_eq!(res.iter().next().unwrap().to_string(), MAX_DICE.to_string());
+        let too_many = format!("{}d1", MAX_DICE + 1);
+        assert_eq!(
+            eval_from_str(&too_many),
+            Err(ExpressionError::Overflow(too_many.clone()))
+        );
+
+        let res = eval_from_str("d666666666666666666666");
+        assert_eq!(
+            res,
+            Err(ExpressionError::Overflow(
+                "d666666666666666666666".to_string()
+            ))
+        );
+
+        let expr = "2000000000; 2000000000".parse::<ExprKind>().unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(
+            expr.best_of(&mut rng, 1),
+            Err(ExpressionError::Overflow(
+                "2000000000;2000000000".to_string()
+            ))
+        );
+        assert!(matches!(
+            expr.sample_distribution(&mut rng, 1),
+            Err(ExpressionError::Overflow(_))
+        ));
+    }
+
+    #[test]
+    fn test_roll_tray() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let expr = "2d6 + 3d8".parse::<ExprKind>().unwrap();
+        let tray = expr.roll_tray(&mut rng).unwrap();
+
+        assert_eq!(tray.len(), 5);
+        assert!(tray[..2].iter().all(|d| d.source == "2d6" && d.sides == 6));
+        assert!(tray[2..].iter().all(|d| d.source == "3d8" && d.sides == 8));
+        assert!(tray.iter().all(|d| (1..=d.sides as i32).contains(&d.face)));
+
+        let expr = "d[-1,0,1] + 4; hp: 2".parse::<ExprKind>().unwrap();
+        let tray = expr.roll_tray(&mut rng).unwrap();
+        assert_eq!(tray.len(), 1);
+        assert_eq!((tray[0].sides, tray[0].source.as_str()), (3, "1d[-1,0,1]"));
+
+        let expr = "(1d1 + 1)d4".parse::<ExprKind>().unwrap();
+        assert_eq!(expr.roll_tray(&mut rng).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_references() {
+        let expr = "atk: 3 + 4; dmg: 2; total: atk + dmg; max(dmg, 1d1)";
+        let res = eval_from_str(expr).unwrap();
+        let totals = res
+            .iter()
+            .map(|kind| match kind {
+                ExprKind::Simple(expr) | ExprKind::Labeled(_, expr) => expr.get_num(),
+                ExprKind::Separated(_) => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(totals, [Some(7), Some(2), Some(9), Some(2)]);
+
+        let res = eval_from_str("atk: 1d20 + 5; dmg: 2d6; total: atk + dmg").unwrap();
+        let parts = res.iter().collect::<Vec<_>>();
+        let num = |i: usize| match parts[i] {
+            ExprKind::Labeled(_, expr) => expr.get_num().unwrap(),
+            _ => unreachable!(),
+        };
+        assert_eq!(num(2), num(0) + num(1));
+
+        assert_eq!(
+            eval_from_str("dmg + 1; dmg: 2"),
+            Err(ExpressionError::UnknownRoll("dmg".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_overflow() {
+        for (src, dice) in [
+            ("99999999999d6", "99999999999d6"),
+            ("2d99999999999", "2d99999999999"),
+            ("1 + 99999999999d6", "99999999999d6"),
+            ("hp: 3; big: max(1, 2d99999999999)", "2d99999999999"),
+        ] {
+            let reason = format!("Dice expression has a number that is too large: `{dice}`");
+            assert_eq!(
+                src.parse::<ExprKind>(),
+                Err(ExpressionError::ParseError(reason)),
+                "{src} should not parse"
+            );
+            assert!(src.parse::<Expr>().is_err(), "{src} should not parse");
+        }
+
+        for src in ["3 + 99999999999", "(1 + 99999999999)d6"] {
+            assert!(
+                matches!(src.parse::<ExprKind>(), Err(ExpressionError::ParseError(_))),
+                "{src} should not parse"
+            );
+            assert!(src.parse::<Expr>().is_err(), "{src} should not parse");
+        }
+
+        assert_eq!(
+            "1d6 + 2 )".parse::<Expr>(),
+            Err(ExpressionError::ParseError(
+                "unexpected input `)`".to_string()
+            ))
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_events() {
+        use std::sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        };
+
+        use tracing::{Event, Metadata, Subscriber, span};
+
+        /// Counts the events about rolled dice and the results worked out from them
+        struct EventCounter {
+            dice: Arc<AtomicUsize>,
+            results: Arc<AtomicUsize>,
+        }
+
+        impl Subscriber for EventCounter {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+                span::Id::from_u64(1)
+            }
+
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                let fields = event.metadata().fields();
+                if fields.field("face").is_some() {
+                    self.dice.fetch_add(1, Ordering::SeqCst);
+                }
+                if fields.field("result").is_some() {
+                    self.results.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+
+            fn enter(&self, _: &span::Id) {}
+
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let dice = Arc::new(AtomicUsize::new(0));
+        let results = Arc::new(AtomicUsize::new(0));
+        let counter = EventCounter {
+            dice: dice.clone(),
+            results: results.clone(),
+        };
+        tracing::subscriber::with_default(counter, || {
+            eval_from_str("2d6 + 3d8; 1d4").unwrap();
+            eval_from_str("max(1d20, 1d20) - 1").unwrap();
+        });
+        assert_eq!(dice.load(Ordering::SeqCst), 8);
+        assert_eq!(results.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_bounds() {
+        let expr = "1d20 + 5".parse::<Expr>().unwrap();
+        assert_eq!((expr.min_value(), expr.max_value()), (6, 25));
+
+        let expr = "10 - 1d6".parse::<Expr>().unwrap();
+        assert_eq!((expr.min_value(), expr.max_value()), (4, 9));
+
+        let expr = "3d4 - 2d6".parse::<Expr>().unwrap();
+        assert_eq!((expr.min_value(), expr.max_value()), (-9, 10));
+
+        let expr = "3d4 - 2d6 + 1".parse::<Expr>().unwrap();
+        assert_eq!((expr.min_value(), expr.max_value()), (-8, 11));
+
+        // References could be anything until they are resolved
+        let expr = "max(atk, 1d6) + 2".parse::<Expr>().unwrap();
+        assert_eq!(expr.try_bounds(), None);
+        assert_eq!((expr.min_value(), expr.max_value()), (i32::MIN, i32::MAX));
+        let values = HashMap::from([("atk".to_string(), 4)]);
+        assert_eq!(expr.resolve(&values).try_bounds(), Some((6, 8)));
+
+        // Dice without sides always fail to evaluate, unless there are none of them
+        for src in ["2d0", "(1d4)d0", "1d0 + 5", "(1 - 3)d6"] {
+            let expr = src.parse::<Expr>().unwrap();
+            assert_eq!(expr.try_bounds(), None, "{src}");
+        }
+        for src in ["0d0", "(1d2 - 1)d0"] {
+            let expr = src.parse::<Expr>().unwrap();
+            assert_eq!(expr.try_bounds(), Some((0, 0)), "{src}");
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        for src in ["", "   ", "\n\t "] {
+            assert_eq!(
+                src.parse::<ExprKind>(),
+                Err(ExpressionError::EmptyExpression)
+            );
+            assert_eq!(src.parse::<Expr>(), Err(ExpressionError::EmptyExpression));
+        }
+    }
+
+    #[test]
+    fn test_functions() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..100 {
+            let res = "max(1d20, 10)".parse::<Expr>().unwrap().eval_with(&mut rng);
+            assert!((10..=20).contains(&res.unwrap().get_num().unwrap()));
+
+            let res = "min(2d6, 6)".parse::<Expr>().unwrap().eval_with(&mut rng);
+            assert!((2..=6).contains(&res.unwrap().get_num().unwrap()));
+        }
+
+        let res = "max(0, 1d6 - 10)".parse::<Expr>().unwrap().eval();
+        assert_eq!(res.unwrap().get_num(), Some(0));
+    }
+
+    #[test]
+    fn test_function_bounds() {
+        let expr = "max(1d20, 10) + min(2d6, 6)".parse::<Expr>().unwrap();
+        assert_eq!((expr.min_value(), expr.max_value()), (12, 26));
+    }
+
+    #[test]
+    fn test_seeded_eval() {
+        let expr = "4d6 + 2d8; hp: 3d6 - 1d4; max(1d20, 1d20)"
+            .parse::<ExprKind>()
+            .unwrap();
+
+        let first = expr.clone().eval_with(&mut StdRng::seed_from_u64(1234));
+        let second = expr.eval_with(&mut StdRng::seed_from_u64(1234));
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_evaluation_error_context() {
+        let expr = Expr::Application(
+            Operation::Add,
+            (
+                Box::new(Expr::Constant(Atom::Operation(Operation::Sub))),
+                Box::new(1.into()),
+            ),
+        );
+        let err = expr.eval().unwrap_err();
+        assert_eq!(err.to_string(), "could not evaluate `-`: expected a number");
+
+        let err = Expr::Function(Function::Max, vec![]).eval().unwrap_err();
+        assert_eq!(
+            err,
+            ExpressionError::EvaluationError {
+                expr: "max()".to_string(),
+                reason: "a function needs at least one argument".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_dynamic_dice() {
+        let expr = "(1d4)d6".parse::<Expr>().unwrap();
+        assert_eq!((expr.min_value(), expr.max_value()), (1, 24));
+
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..100 {
+            let res = expr.clone().eval_with(&mut rng).unwrap().get_num().unwrap();
+            assert!((1..=24).contains(&res));
+        }
+
+        let err = "(1 - 3)d6".parse::<Expr>().unwrap().eval().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "could not evaluate `(1 - 3)d6`: the number of dice can't be negative"
+        );
+    }
+
+    #[test]
+    fn test_custom_dice() {
+        let expr = "3d[1,1,3,5]".parse::<Expr>().unwrap();
+        assert_eq!((expr.min_value(), expr.max_value()), (3, 15));
+
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..100 {
+            let res = expr.clone().eval_with(&mut rng).unwrap().get_num().unwrap();
+            assert!([3, 5, 7, 9, 11, 13, 15].contains(&res));
+        }
+    }
+
+    #[test]
+    fn test_concat_dice() {
+        let expr = "d66".parse::<Expr>().unwrap();
+        assert_eq!((expr.min_value(), expr.max_value()), (11, 66));
+
+        let mut rng = StdRng::seed_from_u64(6);
+        for _ in 0..100 {
+            let res = expr.clone().eval_with(&mut rng).unwrap().get_num().unwrap();
+            assert!((1..=6).contains(&(res / 10)) && (1..=6).contains(&(res % 10)));
+        }
+    }
 }
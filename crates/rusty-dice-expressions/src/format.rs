@@ -0,0 +1,49 @@
+use rand::Rng;
+
+use crate::{Eval, ExprKind};
+
+/// Parse, evaluate and format an expression in one go, rolling all dice with the provided generator
+///
+/// Every part of the expression ends up on its own line, as `<expression>: <total>`
+/// for unlabeled parts and `<label>: <total>` for labeled ones.
+/// Errors are formatted in place of the result, so the output can always be shown as-is
+pub fn roll_expression_with<R: Rng + ?Sized>(input: &str, rng: &mut R) -> String {
+    let parsed = match input.parse::<ExprKind>() {
+        Ok(parsed) => parsed,
+        Err(err) => return err.to_string(),
+    };
+
+    let evaluated = match parsed.clone().eval_with(rng) {
+        Ok(evaluated) => evaluated,
+        Err(err) => return err.to_string(),
+    };
+
+    parsed
+        .iter()
+        .zip(evaluated.iter())
+        .map(|(before, after)| match (before, after) {
+            (ExprKind::Simple(expr), ExprKind::Simple(total)) => format!("{expr}: {total}"),
+            _ => after.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    #[test]
+    fn test_roll_expression() {
+        let mut rng = StdRng::seed_from_u64(42);
+
+        assert_eq!(roll_expression_with("3 + 1", &mut rng), "3 + 1: 4");
+        assert_eq!(
+            roll_expression_with("hp: 10; 2 - 1", &mut rng),
+            "hp: 10\n2 - 1: 1"
+        );
+        assert_eq!(roll_expression_with("   ", &mut rng), "expression is empty");
+    }
+}
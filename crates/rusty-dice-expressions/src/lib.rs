@@ -47,6 +47,23 @@
 //!
 //! Note that the [`ExprKind`] enum does not support the `get_num` method,
 //! as it can either have one or multiple results associated with it
+//!
+//! Evaluation uses the thread-local random number generator by default.
+//! To get reproducible results, pass your own generator to [`Eval::eval_with`]
+//!
+//! ```rust
+//! use rand::{SeedableRng, rngs::StdRng};
+//! use rusty_dice_expressions::{Expr, Eval};
+//!
+//! # fn main() -> Result<(), rusty_dice_expressions::ExpressionError> {
+//! let parsed = "2d20 + 3".parse::<Expr>()?;
+//!
+//! let first = parsed.clone().eval_with(&mut StdRng::seed_from_u64(42))?;
+//! let second = parsed.eval_with(&mut StdRng::seed_from_u64(42))?;
+//! assert_eq!(first, second);
+//! # Ok(())
+//! # }
+//! ```
 #![warn(missing_docs)]
 
 use thiserror::Error;
@@ -64,8 +81,29 @@ pub mod eval;
 /// The parsers are created with [`nom`]
 pub mod parse;
 
+/// Registry module
+///
+/// Contains [`RollRegistry`], a store of named expressions
+pub mod registry;
+
+/// Formatting module
+///
+/// Contains [`format::roll_expression_with`], which parses, evaluates
+/// and formats an expression in one go
+pub mod format;
+
+/// WASM module
+///
+/// Contains [`roll_expression`], a single entry point for web frontends
+/// exported through `wasm-bindgen`
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
 pub use eval::Eval;
 pub use parse::{Expr, ExprKind};
+pub use registry::RollRegistry;
+#[cfg(feature = "wasm")]
+pub use wasm::roll_expression;
 
 /// Errors that can happen when interacting with this crate
 #[derive(Debug, Error, PartialEq, Eq)]
@@ -78,7 +116,33 @@ pub enum ExpressionError {
 
     /// Evaluation error
     ///
-    /// Happens when an expression cannot be evaluated
-    #[error("could not evaluate expression")]
-    EvaluationError,
+    /// Happens when an expression cannot be evaluated.
+    /// Holds the offending sub-expression and the reason it failed
+    #[error("could not evaluate `{expr}`: {reason}")]
+    EvaluationError {
+        /// The sub-expression that failed to evaluate
+        expr: String,
+
+        /// Why the evaluation failed
+        reason: String,
+    },
+
+    /// Empty expression error
+    ///
+    /// Happens when the input has nothing but whitespace in it
+    #[error("expression is empty")]
+    EmptyExpression,
+
+    /// Unknown roll error
+    ///
+    /// Happens when looking up a named roll that was never registered
+    #[error("no roll named `{0}`")]
+    UnknownRoll(String),
+
+    /// Overflow error
+    ///
+    /// Happens when a result doesn't fit into an `i32`.
+    /// Holds the sub-expression that overflowed
+    #[error("`{0}` is too large to evaluate")]
+    Overflow(String),
 }
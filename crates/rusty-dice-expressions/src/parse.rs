@@ -3,16 +3,45 @@ use core::fmt;
 use nom::{
     IResult, Parser,
     branch::alt,
-    bytes::complete::{tag, take_until},
-    character::complete::{digit1, multispace0, one_of},
-    combinator::{map, map_res, recognize},
-    error::Error,
-    multi::separated_list1,
-    sequence::{preceded, separated_pair},
+    bytes::complete::{tag, take_till},
+    character::complete::{alpha1, alphanumeric1, digit1, multispace0, one_of, satisfy},
+    combinator::{map, map_opt, map_res, not, opt, recognize, verify},
+    error::{ErrorKind, FromExternalError, ParseError},
+    multi::{many0, many1, separated_list1},
+    sequence::{delimited, pair, preceded, separated_pair, terminated},
 };
-use rusty_dice::Dice;
+use rusty_dice::{CustomDice, Dice};
 
-type ParseRes<'a, T> = IResult<&'a str, T, Error<&'a str>>;
+pub(crate) type ParseRes<'a, T> = IResult<&'a str, T, ParseFailure<'a>>;
+
+/// The error produced by the parsers
+///
+/// Points at the input that couldn't be parsed, and for input that can't be anything else,
+/// like dice too large to roll, also says why it was rejected
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct ParseFailure<'a> {
+    pub(crate) input: &'a str,
+    pub(crate) reason: Option<String>,
+}
+
+impl<'a> ParseError<&'a str> for ParseFailure<'a> {
+    fn from_error_kind(input: &'a str, _: ErrorKind) -> Self {
+        ParseFailure {
+            input,
+            reason: None,
+        }
+    }
+
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<'a, E> FromExternalError<&'a str, E> for ParseFailure<'a> {
+    fn from_external_error(input: &'a str, kind: ErrorKind, _: E) -> Self {
+        Self::from_error_kind(input, kind)
+    }
+}
 
 /// Mathematical operations supported by this crate
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -39,6 +68,31 @@ impl fmt::Display for Operation {
     }
 }
 
+/// Functions supported by this crate
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Function {
+    /// The smallest of the arguments
+    ///
+    /// Example: "min(2d6, 6)"
+    Min,
+
+    /// The largest of the arguments
+    ///
+    /// Example: "max(0, 1d6 - 5)"
+    Max,
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let repr = match self {
+            Function::Min => "min",
+            Function::Max => "max",
+        };
+
+        write!(f, "{}", repr)
+    }
+}
+
 /// Atoms of an expression
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Atom {
@@ -47,6 +101,23 @@ pub enum Atom {
     /// Example: "2d6"
     Dice(Dice),
 
+    /// A dice notation with a list of face values
+    ///
+    /// Examples: "d[1,1,3,5]", "4d[-1,0,1]"
+    ///
+    /// Averaging dice have a shorthand notation, e.g. "3dA" is the same as "3d[2,3,3,4,4,5]"
+    ///
+    /// The quantity can be omitted, in which case it is 1
+    CustomDice(CustomDice),
+
+    /// Dice whose results are read as the digits of one number
+    ///
+    /// Examples: "d66", "d666"
+    ///
+    /// The number of digits gives the quantity, and the digit itself gives the number of sides,
+    /// so "d66" is two six-sided dice giving values of 11 to 66
+    ConcatDice(Dice),
+
     /// A number
     ///
     /// Examples: "42", "-13"
@@ -58,6 +129,16 @@ pub enum Atom {
     ///
     /// Example: "+"
     Operation(Operation),
+
+    /// A reference to the result of an earlier labeled expression
+    ///
+    /// Example: "atk" in "atk: 1d20 + 5; dmg: 2d6; total: atk + dmg"
+    ///
+    /// Only labels made of letters, digits and underscores can be referenced,
+    /// and a label has to come before the expressions that use it
+    ///
+    /// Names that start like dice notation, such as "d6" or "d10d", are not references
+    Reference(String),
 }
 
 impl Atom {
@@ -90,8 +171,14 @@ impl fmt::Display for Atom {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let inner = match self {
             Atom::Dice(dice) => dice.to_string(),
+            Atom::CustomDice(dice) => dice.to_string(),
+            Atom::ConcatDice(dice) => {
+                let digits = dice.num_sides.to_string().repeat(dice.quantity as usize);
+                format!("d{digits}")
+            }
             Atom::Number(n) => n.to_string(),
             Atom::Operation(operation) => operation.to_string(),
+            Atom::Reference(name) => name.clone(),
         };
         write!(f, "{}", inner)
     }
@@ -134,9 +221,29 @@ pub enum Expr {
     ///
     /// Example: "5d6 + 1d4 + 5"
     ///
-    /// The operands for the first addition are `5d6` and `1d4 + 5`,
-    /// which is itself an Application expr
+    /// Operations are grouped from the left, so the operands for the second addition
+    /// are `5d6 + 1d4`, which is itself an Application expr, and `5`
     Application(Operation, (Box<Expr>, Box<Expr>)),
+
+    /// A function applied to one or more comma-separated expressions
+    ///
+    /// Example: "max(1d20, 10)"
+    Function(Function, Vec<Expr>),
+
+    /// A dice roll where the quantity and/or the number of sides are expressions themselves
+    ///
+    /// Example: "(1d4)d6"
+    ///
+    /// The quantity and sides are evaluated first, and then the resulting dice are rolled
+    DynamicDice(Box<Expr>, Box<Expr>),
+}
+
+/// Wrap the part of a dice notation in parentheses, unless it is a plain number
+fn dice_part(expr: &Expr) -> String {
+    match expr {
+        Expr::Constant(Atom::Number(n)) if *n >= 0 => n.to_string(),
+        _ => format!("({expr})"),
+    }
 }
 
 impl fmt::Display for Expr {
@@ -146,6 +253,18 @@ impl fmt::Display for Expr {
             Expr::Application(expr, (l, r)) => {
                 format!("{} {} {}", l.to_string(), expr.to_string(), r.to_string())
             }
+            Expr::Function(func, args) => {
+                let args = args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+                format!("{func}({})", args.join(", "))
+            }
+            Expr::DynamicDice(quantity, num_sides) => {
+                let quantity = match (quantity.get_num(), num_sides.get_num()) {
+                    // Keeps "(2)d6" from reading back as the plain dice "2d6"
+                    (Some(q), Some(s)) if q >= 0 && s >= 0 => format!("({quantity})"),
+                    _ => dice_part(quantity),
+                };
+                format!("{quantity}d{}", dice_part(num_sides))
+            }
         };
         write!(f, "{}", repr)
     }
@@ -162,6 +281,57 @@ impl Expr {
             _ => None,
         }
     }
+
+    /// Render the expression as an indented tree, one node per line
+    ///
+    /// Unlike the [`Display`](fmt::Display) implementation, which reproduces the source notation,
+    /// this shows how the expression was actually parsed, which is handy for debugging
+    ///
+    /// Example: "1d6 + 2" becomes
+    ///
+    /// ```text
+    /// Application(+)
+    ///   Constant(Dice 1d6)
+    ///   Constant(Number 2)
+    /// ```
+    pub fn pretty_tree(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(0, &mut out);
+        out
+    }
+
+    fn write_tree(&self, depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        match self {
+            Expr::Constant(atom) => {
+                let kind = match atom {
+                    Atom::Dice(_) => "Dice",
+                    Atom::CustomDice(_) => "CustomDice",
+                    Atom::ConcatDice(_) => "ConcatDice",
+                    Atom::Number(_) => "Number",
+                    Atom::Operation(_) => "Operation",
+                    Atom::Reference(_) => "Reference",
+                };
+                out.push_str(&format!("{indent}Constant({kind} {atom})\n"));
+            }
+            Expr::Application(op, (l, r)) => {
+                out.push_str(&format!("{indent}Application({op})\n"));
+                l.write_tree(depth + 1, out);
+                r.write_tree(depth + 1, out);
+            }
+            Expr::Function(func, args) => {
+                out.push_str(&format!("{indent}Function({func})\n"));
+                for arg in args {
+                    arg.write_tree(depth + 1, out);
+                }
+            }
+            Expr::DynamicDice(quantity, num_sides) => {
+                out.push_str(&format!("{indent}DynamicDice\n"));
+                quantity.write_tree(depth + 1, out);
+                num_sides.write_tree(depth + 1, out);
+            }
+        }
+    }
 }
 
 impl Into<Expr> for Dice {
@@ -198,7 +368,47 @@ pub enum ExprKind {
     Separated(Vec<ExprKind>),
 }
 
-impl ExprKind {}
+impl ExprKind {
+    /// Iterate over the individual expressions
+    ///
+    /// Yields every sub-expression of a [`ExprKind::Separated`] expression,
+    /// and just the expression itself for the other kinds
+    pub fn iter(&self) -> impl Iterator<Item = &ExprKind> {
+        match self {
+            ExprKind::Separated(expr_kinds) => expr_kinds.iter(),
+            _ => std::slice::from_ref(self).iter(),
+        }
+    }
+
+    /// Render the expression as an indented tree, one node per line
+    ///
+    /// See [`Expr::pretty_tree`] for details
+    pub fn pretty_tree(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(0, &mut out);
+        out
+    }
+
+    fn write_tree(&self, depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        match self {
+            ExprKind::Simple(expr) => {
+                out.push_str(&format!("{indent}Simple\n"));
+                expr.write_tree(depth + 1, out);
+            }
+            ExprKind::Labeled(l, expr) => {
+                out.push_str(&format!("{indent}Labeled({l})\n"));
+                expr.write_tree(depth + 1, out);
+            }
+            ExprKind::Separated(expr_kinds) => {
+                out.push_str(&format!("{indent}Separated\n"));
+                for kind in expr_kinds {
+                    kind.write_tree(depth + 1, out);
+                }
+            }
+        }
+    }
+}
 
 impl fmt::Display for ExprKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -230,52 +440,180 @@ fn parse_operation(i: &str) -> ParseRes<Atom> {
 }
 
 fn parse_dice(i: &str) -> ParseRes<Atom> {
+    let (rest, dice_str) = recognize(separated_pair(digit1, tag("d"), digit1)).parse(i)?;
+    match dice_str.parse::<Dice>() {
+        Ok(dice) => Ok((rest, Atom::Dice(dice))),
+        // Nothing else can parse dice notation, so the reason is kept instead of backtracking
+        Err(err) => Err(nom::Err::Failure(ParseFailure {
+            input: i,
+            reason: Some(err.to_string()),
+        })),
+    }
+}
+
+fn parse_custom_dice(i: &str) -> ParseRes<'_, Atom> {
+    let face = map_res(recognize(preceded(opt(tag("-")), digit1)), |face: &str| {
+        face.parse::<i32>()
+    });
+
+    let faces = alt((
+        delimited(
+            tag("d["),
+            separated_list1(tag(","), delimited(multispace0, face, multispace0)),
+            tag("]"),
+        ),
+        // Names like "dAgger" are references rather than averaging dice
+        map(
+            terminated(tag("dA"), not(satisfy(|c| c.is_alphanumeric() || c == '_'))),
+            |_| CustomDice::averaging(1).faces,
+        ),
+    ));
+
     map(
-        recognize(separated_pair(digit1, tag("d"), digit1)),
-        |dice_str: &str| Atom::Dice(dice_str.parse::<Dice>().unwrap()),
+        (
+            opt(map_res(digit1, |digit_str: &str| digit_str.parse::<u32>())),
+            faces,
+        ),
+        |(quantity, faces)| Atom::CustomDice(CustomDice::new(quantity.unwrap_or(1), faces)),
     )
     .parse(i)
 }
 
+fn parse_concat_dice(i: &str) -> ParseRes<'_, Atom> {
+    map_opt(preceded(tag("d"), digit1), |digits: &str| {
+        let first = digits.chars().next()?;
+        let same = digits.chars().all(|d| d == first);
+        if digits.len() < 2 || !same || !('2'..='9').contains(&first) {
+            return None;
+        }
+
+        let num_sides = first.to_digit(10)?;
+        let quantity = u32::try_from(digits.len()).ok()?;
+        Some(Atom::ConcatDice(Dice::new(quantity, num_sides)))
+    })
+    .parse(i)
+}
+
 fn parse_num(i: &str) -> ParseRes<Atom> {
-    alt((
-        map_res(digit1, |digit_str: &str| {
-            digit_str.parse::<i32>().map(Atom::Number)
-        }),
-        map(preceded(tag("-"), digit1), |digit_str: &str| {
-            Atom::Number(-digit_str.parse::<i32>().unwrap())
-        }),
-    ))
+    // The sign is parsed along with the digits, so that i32::MIN fits
+    map_res(
+        recognize(preceded(opt(tag("-")), digit1)),
+        |num_str: &str| num_str.parse::<i32>().map(Atom::Number),
+    )
+    .parse(i)
+}
+
+fn parse_reference(i: &str) -> ParseRes<'_, Atom> {
+    // Something like "d6" or "d10d" is a typo of dice notation rather than a label
+    let is_dice = |name: &&str| {
+        name.strip_prefix('d')
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+    };
+
+    map(
+        verify(
+            recognize(pair(
+                alt((alpha1, tag("_"))),
+                many0(alt((alphanumeric1, tag("_")))),
+            )),
+            |name| !is_dice(name),
+        ),
+        |name: &str| Atom::Reference(name.to_string()),
+    )
     .parse(i)
 }
 
 fn parse_atom(i: &str) -> ParseRes<Atom> {
-    alt((parse_dice, parse_num, parse_operation)).parse(i)
+    alt((
+        parse_custom_dice,
+        parse_concat_dice,
+        parse_dice,
+        parse_num,
+        parse_operation,
+        parse_reference,
+    ))
+    .parse(i)
 }
 
 fn parse_constant(i: &str) -> ParseRes<Expr> {
     map(parse_atom, Expr::Constant).parse(i)
 }
 
+fn parse_function(i: &str) -> ParseRes<'_, Expr> {
+    map(
+        (
+            alt((
+                map(tag("min"), |_| Function::Min),
+                map(tag("max"), |_| Function::Max),
+            )),
+            delimited(
+                preceded(multispace0, tag("(")),
+                separated_list1(preceded(multispace0, tag(",")), parse_expr),
+                preceded(multispace0, tag(")")),
+            ),
+        ),
+        |(func, args)| Expr::Function(func, args),
+    )
+    .parse(i)
+}
+
+fn parse_parenthesized(i: &str) -> ParseRes<'_, Expr> {
+    delimited(tag("("), parse_expr, preceded(multispace0, tag(")"))).parse(i)
+}
+
+fn parse_dice_number(i: &str) -> ParseRes<'_, Expr> {
+    map_res(digit1, |digit_str: &str| {
+        digit_str
+            .parse::<i32>()
+            .map(|n| Expr::Constant(Atom::Number(n)))
+    })
+    .parse(i)
+}
+
+/// Dice with a parenthesized quantity or number of sides, like "(1d4)d6" or "2d(1d4 + 4)"
+///
+/// Plain dice like "2d6" are left to [`parse_dice`]
+fn parse_dynamic_dice(i: &str) -> ParseRes<'_, Expr> {
+    map(
+        alt((
+            separated_pair(
+                parse_parenthesized,
+                tag("d"),
+                alt((parse_parenthesized, parse_dice_number)),
+            ),
+            separated_pair(parse_dice_number, tag("d"), parse_parenthesized),
+        )),
+        |(quantity, num_sides)| Expr::DynamicDice(Box::new(quantity), Box::new(num_sides)),
+    )
+    .parse(i)
+}
+
+/// A single operand of an application
+fn parse_term(i: &str) -> ParseRes<'_, Expr> {
+    alt((parse_function, parse_dynamic_dice, parse_constant)).parse(i)
+}
+
 fn parse_application(i: &str) -> ParseRes<Expr> {
+    // Operations are grouped from the left, so "10 - 2 - 3" is "(10 - 2) - 3"
     map(
         (
-            preceded(multispace0, parse_atom),
-            preceded(multispace0, parse_operation),
-            parse_expr,
+            preceded(multispace0, parse_term),
+            many1(pair(
+                preceded(multispace0, parse_operation),
+                preceded(multispace0, parse_term),
+            )),
         ),
-        |(left, op, right)| {
-            Expr::Application(
-                op.operation().unwrap(),
-                (Box::new(Expr::Constant(left)), Box::new(right)),
-            )
+        |(first, rest)| {
+            rest.into_iter().fold(first, |left, (op, right)| {
+                Expr::Application(op.operation().unwrap(), (Box::new(left), Box::new(right)))
+            })
         },
     )
     .parse(i)
 }
 
 pub(crate) fn parse_expr(i: &str) -> ParseRes<Expr> {
-    preceded(multispace0, alt((parse_application, parse_constant))).parse(i)
+    preceded(multispace0, alt((parse_application, parse_term))).parse(i)
 }
 
 fn parse_simple(i: &str) -> ParseRes<ExprKind> {
@@ -284,20 +622,38 @@ fn parse_simple(i: &str) -> ParseRes<ExprKind> {
 
 fn parse_labeled(i: &str) -> ParseRes<ExprKind> {
     map(
-        separated_pair(preceded(multispace0, take_until(":")), tag(":"), parse_expr),
+        separated_pair(
+            preceded(multispace0, take_till(|c| c == ':' || c == ';')),
+            tag(":"),
+            parse_expr,
+        ),
         |(label, expr)| ExprKind::Labeled(label.to_string(), expr),
     )
     .parse(i)
 }
 
 fn parse_expr_kind_unit(i: &str) -> ParseRes<ExprKind> {
-    alt((parse_simple, parse_labeled)).parse(i)
+    // Labels go first, so that a label isn't mistaken for a reference
+    alt((parse_labeled, parse_simple)).parse(i)
+}
+
+/// One or more ";", so that empty segments like in "1d6;;2d6" are skipped
+fn parse_separator(i: &str) -> ParseRes<'_, ()> {
+    map(many1(preceded(multispace0, tag(";"))), |_| ()).parse(i)
 }
 
 fn parse_separated(i: &str) -> ParseRes<ExprKind> {
     map(
-        separated_list1(preceded(multispace0, tag(";")), parse_expr_kind_unit),
-        |exprs| ExprKind::Separated(exprs),
+        delimited(
+            opt(parse_separator),
+            separated_list1(parse_separator, parse_expr_kind_unit),
+            (opt(parse_separator), multispace0),
+        ),
+        // A lone expression is not separated from anything
+        |mut exprs| match exprs.len() {
+            1 => exprs.remove(0),
+            _ => ExprKind::Separated(exprs),
+        },
     )
     .parse(i)
 }
@@ -308,6 +664,8 @@ pub(crate) fn parse_expr_kind(i: &str) -> ParseRes<ExprKind> {
 
 #[cfg(test)]
 mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
     use super::*;
 
     fn application(op: Operation, left: impl Into<Atom>, right: impl Into<Atom>) -> Expr {
@@ -346,6 +704,14 @@ mod tests {
         assert_eq!(die, Dice::new(12, 20).into());
     }
 
+    #[test]
+    fn test_parse_die_overflow() {
+        assert!(parse_dice("99999999999d6").is_err());
+        assert!(parse_dice("6d99999999999").is_err());
+        assert!(parse_num("99999999999").is_err());
+        assert!(parse_num("-99999999999").is_err());
+    }
+
     #[test]
     fn test_parse_num() {
         let num = "-1234";
@@ -392,6 +758,32 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_parse_reference() {
+        let sep = "atk: 1d20; total: atk + 2";
+        let (i, sep) = parse_separated(sep).unwrap();
+        assert_eq!(i, "");
+        assert_eq!(
+            sep,
+            separated_expr_kind(&[
+                labeled_expr_kind("atk", Dice::new(1, 20)),
+                labeled_expr_kind(
+                    "total",
+                    application(Operation::Add, Atom::Reference("atk".to_string()), 2)
+                ),
+            ])
+        );
+
+        for typo in ["d6", "d10d", "d2x"] {
+            assert!(parse_reference(typo).is_err(), "{typo} should not parse");
+        }
+        assert!(parse_reference("dmg2").is_ok());
+        assert_eq!(
+            parse_reference("str_mod2"),
+            Ok(("", Atom::Reference("str_mod2".to_string())))
+        );
+    }
+
     #[test]
     fn test_dice_repr() {
         let atom: Atom = Dice::new(2, 10).into();
@@ -403,4 +795,275 @@ mod tests {
         let expr: Expr = application(Operation::Add, Dice::new(1, 6), 5);
         assert_eq!(format!("{}", expr), "1d6 + 5");
     }
+
+    #[test]
+    fn test_pretty_tree() {
+        let (_, expr) = parse_expr("1d6 + 2 - 3").unwrap();
+        assert_eq!(
+            expr.pretty_tree(),
+            "Application(-)\n  Application(+)\n    Constant(Dice 1d6)\n    Constant(Number 2)\n  Constant(Number 3)\n"
+        );
+    }
+
+    #[test]
+    fn test_pretty_tree_kind() {
+        let (_, expr) = parse_expr_kind("1d4; hp: 3d6").unwrap();
+        assert_eq!(
+            expr.pretty_tree(),
+            "Separated\n  Simple\n    Constant(Dice 1d4)\n  Labeled(hp)\n    Constant(Dice 3d6)\n"
+        );
+    }
+
+    #[test]
+    fn test_iter() {
+        let (_, sep) = parse_expr_kind("1d6 + 3; my roll: 1d4").unwrap();
+        let items = sep.iter().collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            [
+                &simple_expr_kind(application(Operation::Add, Dice::new(1, 6), 3)),
+                &labeled_expr_kind("my roll", Dice::new(1, 4)),
+            ]
+        );
+
+        let single = simple_expr_kind(Dice::new(2, 8));
+        assert_eq!(single.iter().collect::<Vec<_>>(), [&single]);
+    }
+
+    #[test]
+    fn test_parse_separated_empty_segments() {
+        let expected = separated_expr_kind(&[
+            simple_expr_kind(Dice::new(1, 6)),
+            simple_expr_kind(Dice::new(2, 6)),
+        ]);
+
+        for sep in ["1d6;;2d6", "1d6; 2d6;", ";1d6 ;\n\n; 2d6;\n"] {
+            let (i, res) = parse_separated(sep).unwrap();
+            assert_eq!(i, "", "input: {sep:?}");
+            assert_eq!(res, expected, "input: {sep:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_function() {
+        let (i, func) = parse_expr("max(1d20, 10)").unwrap();
+        assert_eq!(i, "");
+        assert_eq!(
+            func,
+            Expr::Function(Function::Max, vec![Dice::new(1, 20).into(), 10.into()])
+        );
+
+        let (i, func) = parse_expr("min( 2d6 ,6 ) + 1").unwrap();
+        assert_eq!(i, "");
+        assert_eq!(
+            func,
+            Expr::Application(
+                Operation::Add,
+                (
+                    Box::new(Expr::Function(
+                        Function::Min,
+                        vec![Dice::new(2, 6).into(), 6.into()]
+                    )),
+                    Box::new(1.into())
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn test_function_repr() {
+        let (_, func) = parse_expr("max(0, 1d6 - 5)").unwrap();
+        assert_eq!(func.to_string(), "max(0, 1d6 - 5)");
+    }
+
+    #[test]
+    fn test_parse_dynamic_dice() {
+        let (i, dice) = parse_expr("(1d4)d6").unwrap();
+        assert_eq!(i, "");
+        assert_eq!(
+            dice,
+            Expr::DynamicDice(Box::new(Dice::new(1, 4).into()), Box::new(6.into()))
+        );
+
+        let (i, dice) = parse_expr("2d(1d4 + 4) + 1").unwrap();
+        assert_eq!(i, "");
+        assert_eq!(
+            dice,
+            Expr::Application(
+                Operation::Add,
+                (
+                    Box::new(Expr::DynamicDice(
+                        Box::new(2.into()),
+                        Box::new(application(Operation::Add, Dice::new(1, 4), 4))
+                    )),
+                    Box::new(1.into())
+                )
+            )
+        );
+
+        let (_, dice) = parse_expr("2d6").unwrap();
+        assert_eq!(dice, Dice::new(2, 6).into());
+    }
+
+    #[test]
+    fn test_dynamic_dice_repr() {
+        for src in ["(1d4)d6", "2d(1d4 + 4)", "(1d2)d(1d6)", "(-1)d4", "(2)d6"] {
+            let (_, dice) = parse_expr(src).unwrap();
+            assert_eq!(dice.to_string(), src);
+        }
+    }
+
+    #[test]
+    fn test_parse_custom_dice() {
+        let (i, die) = parse_atom("d[1,1,3,5]").unwrap();
+        assert_eq!(i, "");
+        assert_eq!(die, Atom::CustomDice(CustomDice::new(1, vec![1, 1, 3, 5])));
+
+        let (i, die) = parse_expr("4d[-1, 0, 1] + 2").unwrap();
+        assert_eq!(i, "");
+        assert_eq!(
+            die,
+            application(
+                Operation::Add,
+                Atom::CustomDice(CustomDice::new(4, vec![-1, 0, 1])),
+                2
+            )
+        );
+        assert_eq!(die.to_string(), "4d[-1,0,1] + 2");
+    }
+
+    #[test]
+    fn test_parse_averaging_dice() {
+        let (i, die) = parse_atom("3dA").unwrap();
+        assert_eq!(i, "");
+        assert_eq!(die, Atom::CustomDice(CustomDice::averaging(3)));
+
+        let (i, sep) = parse_expr_kind("dAgger: 3; dAgger + 1").unwrap();
+        assert_eq!(i, "");
+        assert_eq!(
+            sep,
+            separated_expr_kind(&[
+                labeled_expr_kind("dAgger", 3),
+                simple_expr_kind(application(
+                    Operation::Add,
+                    Atom::Reference("dAgger".to_string()),
+                    1
+                )),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_concat_dice() {
+        let (i, die) = parse_atom("d66").unwrap();
+        assert_eq!(i, "");
+        assert_eq!(die, Atom::ConcatDice(Dice::new(2, 6)));
+        assert_eq!(die.to_string(), "d66");
+
+        let (_, die) = parse_atom("d444").unwrap();
+        assert_eq!(die, Atom::ConcatDice(Dice::new(3, 4)));
+
+        for invalid in ["d6", "d65", "d11", "d00"] {
+            assert!(parse_atom(invalid).is_err(), "{invalid} should not parse");
+        }
+    }
+
+    /// Check that displaying an expression and parsing it back gives the same expression
+    fn assert_roundtrip(expr: &ExprKind) {
+        let repr = expr.to_string();
+        assert_eq!(
+            repr.parse::<ExprKind>().as_ref(),
+            Ok(expr),
+            "repr: {repr:?}"
+        );
+    }
+
+    /// Generate a random expression of the shape the parser produces
+    ///
+    /// The right operand of an application is never an application itself
+    fn random_expr(rng: &mut StdRng, depth: u32) -> Expr {
+        let atom = |rng: &mut StdRng| -> Atom {
+            match rng.random_range(0..6) {
+                0 => Atom::Dice(Dice::new(
+                    rng.random_range(0..20),
+                    rng.random_range(1..=100),
+                )),
+                1 => {
+                    let faces = (0..rng.random_range(1..5))
+                        .map(|_| rng.random_range(-5..=10))
+                        .collect();
+                    Atom::CustomDice(CustomDice::new(rng.random_range(0..5), faces))
+                }
+                2 => Atom::ConcatDice(Dice::new(rng.random_range(2..4), rng.random_range(2..=9))),
+                3 => Atom::Number(rng.random()),
+                4 => Atom::Number(rng.random_range(-10..=10)),
+                _ => Atom::Reference(
+                    ["atk", "dmg", "hp_2", "_bonus"][rng.random_range(0..4)].to_string(),
+                ),
+            }
+        };
+
+        if depth == 0 {
+            return Expr::Constant(atom(rng));
+        }
+
+        let term = |rng: &mut StdRng| match rng.random_range(0..4) {
+            0 => {
+                let args = (0..rng.random_range(1..4))
+                    .map(|_| random_expr(rng, depth - 1))
+                    .collect();
+                Expr::Function([Function::Min, Function::Max][rng.random_range(0..2)], args)
+            }
+            1 => {
+                let inner = Box::new(random_expr(rng, depth - 1));
+                let number = Box::new(Expr::Constant(Atom::Number(rng.random_range(0..10))));
+                if rng.random() {
+                    Expr::DynamicDice(inner, number)
+                } else {
+                    Expr::DynamicDice(number, inner)
+                }
+            }
+            _ => Expr::Constant(atom(rng)),
+        };
+
+        match rng.random_range(0..3) {
+            0 => term(rng),
+            _ => {
+                let op = [Operation::Add, Operation::Sub][rng.random_range(0..2)];
+                let right = term(rng);
+                Expr::Application(op, (Box::new(random_expr(rng, depth - 1)), Box::new(right)))
+            }
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let cases = [
+            "1d6",
+            "2d6 + 5",
+            "10 - 1d6 - -3",
+            "max(0, 1d6 - 2) + min(2d6, 6)",
+            "(1d4)d6 + 2d(1d4 + 4)",
+            "2d(3) + (0)d0",
+            "d[1,1,3,5] + 3dA + d66",
+            "-2147483648 + 2147483647",
+            "hp: 3d6",
+            "hp: 3d6;arrows in pouch: 4d4 + 6;1d20",
+            "atk: 1d20 + 5;total: atk + 2",
+        ];
+        for case in cases {
+            assert_roundtrip(&case.parse::<ExprKind>().unwrap());
+        }
+
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..500 {
+            let expr = random_expr(&mut rng, 3);
+            assert_roundtrip(&ExprKind::Simple(expr.clone()));
+            assert_roundtrip(&ExprKind::Labeled("some label".to_string(), expr.clone()));
+            assert_roundtrip(&ExprKind::Separated(vec![
+                ExprKind::Labeled("atk".to_string(), expr.clone()),
+                ExprKind::Simple(expr),
+            ]));
+        }
+    }
 }
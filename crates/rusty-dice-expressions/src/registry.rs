@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use crate::{ExpressionError, eval::Eval, parse::ExprKind};
+
+/// A collection of expressions that can be looked up by name
+///
+/// Useful for storing reusable rolls, e.g. so that "fireball" always means "8d6"
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RollRegistry {
+    rolls: HashMap<String, ExprKind>,
+}
+
+impl RollRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store an expression under the given name
+    ///
+    /// Returns the expression that was previously stored under that name, if any
+    pub fn register(&mut self, name: impl Into<String>, expr: ExprKind) -> Option<ExprKind> {
+        self.rolls.insert(name.into(), expr)
+    }
+
+    /// Store every labeled expression under its label
+    ///
+    /// Unlabeled expressions are skipped. Returns the number of registered expressions
+    pub fn register_labeled(&mut self, expr: &ExprKind) -> usize {
+        let mut count = 0;
+        for kind in expr.iter() {
+            if let ExprKind::Labeled(label, _) = kind {
+                self.register(label.trim(), kind.clone());
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    /// Look up the expression stored under the given name
+    pub fn get(&self, name: &str) -> Option<&ExprKind> {
+        self.rolls.get(name)
+    }
+
+    /// Evaluate the expression stored under the given name
+    pub fn eval_named(&self, name: &str) -> Result<ExprKind, ExpressionError> {
+        self.get(name)
+            .cloned()
+            .ok_or_else(|| ExpressionError::UnknownRoll(name.to_string()))?
+            .eval()
+    }
+
+    /// Iterate over the names of all stored expressions, in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rolls.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register() {
+        let mut registry = RollRegistry::new();
+        let fireball = "8d6".parse::<ExprKind>().unwrap();
+
+        assert_eq!(registry.register("fireball", fireball.clone()), None);
+        assert_eq!(registry.get("fireball"), Some(&fireball));
+        assert_eq!(registry.get("heal"), None);
+    }
+
+    #[test]
+    fn test_register_labeled() {
+        let mut registry = RollRegistry::new();
+        let exprs = "fireball: 8d6; 1d20; heal: 2 + 2"
+            .parse::<ExprKind>()
+            .unwrap();
+
+        assert_eq!(registry.register_labeled(&exprs), 2);
+        let mut names = registry.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["fireball", "heal"]);
+    }
+
+    #[test]
+    fn test_eval_named() {
+        let mut registry = RollRegistry::new();
+        registry.register_labeled(&"heal: 2 + 2".parse::<ExprKind>().unwrap());
+
+        assert_eq!(
+            registry.eval_named("heal"),
+            Ok(ExprKind::Labeled("heal".to_string(), 4.into()))
+        );
+        assert_eq!(
+            registry.eval_named("fireball"),
+            Err(ExpressionError::UnknownRoll("fireball".to_string()))
+        );
+    }
+}
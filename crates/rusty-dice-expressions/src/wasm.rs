@@ -0,0 +1,12 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::format::roll_expression_with;
+
+/// Parse, evaluate and format an expression in one go
+///
+/// This is [`roll_expression_with`] using the default generator,
+/// exported through `wasm-bindgen` as a single entry point for web frontends
+#[wasm_bindgen]
+pub fn roll_expression(input: &str) -> String {
+    roll_expression_with(input, &mut rand::rng())
+}
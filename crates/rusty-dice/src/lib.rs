@@ -10,6 +10,7 @@
 //! ```rust
 //! use rusty_dice::Dice;
 //!
+//! # #[cfg(feature = "std")]
 //! # fn main() -> Result<(), rusty_dice::DiceError> {
 //! let d6 = Dice::single(6);  // Obtain a die
 //! let result = d6.roll();  // Roll it to get a result
@@ -17,6 +18,8 @@
 //! let result = many_dice.roll();
 //! # Ok(())
 //! # }
+//! # #[cfg(not(feature = "std"))]
+//! # fn main() {}
 //! ```
 //! This crate also provides an easy way to parse dice from text,
 //! following the notation of `XdY`, where X is the number of dice
@@ -30,14 +33,34 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! # Features
+//!
+//! - `std` (enabled by default): rolling with the thread-local and OS random number generators.
+//!   Without it the crate is `no_std` (it still needs `alloc`), and the generator
+//!   always has to be passed explicitly, e.g. through [`Dice::roll_with`]
 #![deny(missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
-use std::{fmt::Display, str::FromStr};
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
+    fmt::Display,
+    num::{IntErrorKind, ParseIntError},
+    str::FromStr,
+};
 
 use rand::Rng;
+#[cfg(feature = "std")]
+use rand::{TryRngCore, rngs::OsRng};
 use thiserror::Error;
 
-#[derive(Error, Debug, PartialEq, Eq)]
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
 /// The errors that can occur when working with this crate
 ///
 /// Typically this crate should not give any errors, but there are rare circumstances
@@ -46,9 +69,13 @@ pub enum DiceError {
     /// Thrown when an attempt to parse a string into [`Dice`] fails
     #[error("Failed to parse dice expression: `{0}`")]
     InvalidExpression(String),
+
+    /// Thrown when the quantity or the number of sides of [`Dice`] doesn't fit into a `u32`
+    #[error("Dice expression has a number that is too large: `{0}`")]
+    Overflow(String),
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 /// The main type, representing one or more fair dice of the same type
 ///
 /// "Fair" means every value has an equal chance of appearing.
@@ -68,14 +95,283 @@ impl Dice {
     ///
     /// If the associated [`Dice`] value has a quantity of greater than 1,
     /// then the result will be a sum of the values
+    #[cfg(feature = "std")]
     pub fn roll(&self) -> Vec<u32> {
-        (1..=self.quantity)
-            .map(|_| rand::rng().random_range(1..=self.num_sides))
-            .collect()
+        self.roll_with(&mut rand::rng())
+    }
+
+    /// Method for rolling the dice with the provided random number generator
+    ///
+    /// Works the same as [`Dice::roll`], but lets the caller decide where the randomness
+    /// comes from, e.g. a seeded generator for reproducible results
+    ///
+    /// Any `num_sides` up to [`u32::MAX`] is fine, since every die is kept as its own `u32`.
+    /// The quantity is at most [`u32::MAX`] as well, but one `u32` is stored per die,
+    /// so the largest quantities need up to 16 GiB. [`Dice::roll_iter_with`] rolls them
+    /// without storing anything. Sum the results with [`total`], which widens to `u64`
+    /// and so can't overflow for any valid dice: even `u32::MAX` dice all showing
+    /// `u32::MAX` stay below [`u64::MAX`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_sides` is 0 while `quantity` isn't, as such a die has nothing to show
+    pub fn roll_with<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<u32> {
+        self.roll_iter_with(rng).collect()
+    }
+
+    /// Method for rolling the dice one die at a time
+    ///
+    /// Works the same as [`Dice::roll_with`], but yields every die as it is rolled
+    /// instead of storing them, so it takes no memory however many dice there are
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_sides` is 0 while `quantity` isn't, as such a die has nothing to show
+    pub fn roll_iter_with<R: Rng + ?Sized>(&self, rng: &mut R) -> impl Iterator<Item = u32> {
+        (1..=self.quantity).map(move |_| rng.random_range(1..=self.num_sides))
+    }
+
+    /// Method for rolling the dice with the operating system's secure random source
+    ///
+    /// Use this when the rolls have real stakes. Any other cryptographically secure
+    /// generator (one implementing [`rand::CryptoRng`]) can be used through [`Dice::roll_with`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system fails to provide random data
+    #[cfg(feature = "std")]
+    pub fn roll_secure(&self) -> Vec<u32> {
+        self.roll_with(&mut OsRng.unwrap_err())
+    }
+
+    /// Method for rolling the dice many times in a row
+    ///
+    /// Returns `samples` separate rolls, all made with the same generator.
+    /// Prefer this over calling [`Dice::roll`] in a loop when running simulations
+    pub fn roll_n<R: Rng + ?Sized>(&self, rng: &mut R, samples: usize) -> Vec<Vec<u32>> {
+        (0..samples).map(|_| self.roll_with(rng)).collect()
+    }
+
+    /// Method for rolling the dice and reading the results as the digits of one number
+    ///
+    /// This is how "d66" style rolls work: two six-sided dice are rolled,
+    /// the first gives the tens and the second gives the ones, for values of 11 to 66.
+    /// A digit is never 0, so e.g. 20 or 37 can't come up
+    ///
+    /// Only dice with at most 9 sides make sense here, since every die has to give a single digit.
+    /// The result saturates at [`u64::MAX`], which happens past 19 dice
+    #[cfg(feature = "std")]
+    pub fn roll_concat(&self) -> u64 {
+        self.roll_concat_with(&mut rand::rng())
+    }
+
+    /// Same as [`Dice::roll_concat`], but rolling with the provided generator
+    pub fn roll_concat_with<R: Rng + ?Sized>(&self, rng: &mut R) -> u64 {
+        self.roll_with(rng).into_iter().fold(0u64, |acc, digit| {
+            acc.saturating_mul(10).saturating_add(u64::from(digit))
+        })
+    }
+
+    /// Method for lazily rolling the dice over and over
+    ///
+    /// Returns an endless iterator of rolls, all made with the same generator,
+    /// so combine it with adapters like [`Iterator::take`]
+    pub fn rolls<R: Rng + ?Sized>(&self, rng: &mut R) -> impl Iterator<Item = Vec<u32>> {
+        core::iter::repeat_with(move || self.roll_with(rng))
+    }
+
+    /// Method for rolling the dice until the result satisfies a condition
+    ///
+    /// Gives up after `cap` attempts. Returns every roll that was made,
+    /// and whether the last one satisfied the predicate
+    pub fn roll_until<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        predicate: impl Fn(&[u32]) -> bool,
+        cap: usize,
+    ) -> (Vec<Vec<u32>>, bool) {
+        let mut attempts = Vec::new();
+        for roll in self.rolls(rng).take(cap) {
+            let done = predicate(&roll);
+            attempts.push(roll);
+            if done {
+                return (attempts, true);
+            }
+        }
+
+        (attempts, false)
+    }
+
+    /// Method for making a roll-under check, as in percentile systems
+    ///
+    /// Succeeds when the total of the roll is at or below the target,
+    /// e.g. `Dice::D100.roll_under(rng, 55)` for a skill of 55
+    pub fn roll_under<R: Rng + ?Sized>(&self, rng: &mut R, target: u32) -> bool {
+        total(&[self.roll_with(rng)]) <= u64::from(target)
+    }
+
+    /// Method for making a roll-under check that tells criticals and fumbles apart
+    ///
+    /// See [`RollUnderResult::new`] for how the total is classified
+    pub fn roll_under_detailed<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        target: u32,
+        thresholds: RollUnderThresholds,
+    ) -> RollUnderResult {
+        RollUnderResult::new(total(&[self.roll_with(rng)]), target, thresholds)
+    }
+
+    /// Method for making a d20 check, as in 5th edition
+    ///
+    /// Rolls a single d20, or two of them with advantage or disadvantage,
+    /// and returns the die that counts
+    pub fn d20_check<R: Rng + ?Sized>(rng: &mut R, advantage: Advantage) -> u32 {
+        let rolls = match advantage {
+            Advantage::Normal => Dice::D20.roll_with(rng),
+            Advantage::Advantage | Advantage::Disadvantage => Dice::new(2, 20).roll_with(rng),
+        };
+
+        let kept = match advantage {
+            Advantage::Disadvantage => rolls.into_iter().min(),
+            Advantage::Normal | Advantage::Advantage => rolls.into_iter().max(),
+        };
+        kept.expect("There should always be at least one d20 rolled")
+    }
+
+    /// The most likely total(s) of the dice
+    ///
+    /// The totals of several dice peak around the middle, with two equally likely totals
+    /// when the middle falls between them, as with `3d6`. A single die is flat,
+    /// so every face is a mode and the result has `num_sides` elements
+    pub fn mode(&self) -> Vec<u64> {
+        let quantity = u64::from(self.quantity);
+        let num_sides = u64::from(self.num_sides);
+
+        match (quantity, num_sides) {
+            (0, _) => vec![0],
+            (_, 0) => vec![],
+            (1, _) => (1..=num_sides).collect(),
+            _ => {
+                // The doubled midpoint, `quantity * (num_sides + 1)`
+                let doubled = quantity * (num_sides + 1);
+                if doubled % 2 == 0 {
+                    vec![doubled / 2]
+                } else {
+                    vec![doubled / 2, doubled / 2 + 1]
+                }
+            }
+        }
+    }
+
+    /// Step the dice one size up the [`DieStep::LADDER`], e.g. from a d6 to a d8
+    ///
+    /// See [`DieStep::step_up`] for what happens at the top of the ladder
+    pub fn step_up(&self) -> DieStep {
+        DieStep::new(*self).step_up()
     }
 
+    /// Step the dice one size down the [`DieStep::LADDER`], e.g. from a d6 to a d4
+    ///
+    /// See [`DieStep::step_down`] for what happens at the bottom of the ladder
+    pub fn step_down(&self) -> DieStep {
+        DieStep::new(*self).step_down()
+    }
+
+    /// The exact expected total when only the `keep` highest dice are kept
+    ///
+    /// E.g. the classic "4d6, drop the lowest" is `Dice::new(4, 6).expected_keep_highest(3)`.
+    /// Keeping more dice than were rolled keeps all of them.
+    /// The computation takes time proportional to `num_sides * quantity`
+    pub fn expected_keep_highest(&self, keep: u32) -> f64 {
+        let keep = keep.min(self.quantity);
+        self.expected_kept(|successes| successes.min(keep))
+    }
+
+    /// The exact expected total when only the `keep` lowest dice are kept
+    ///
+    /// The same rules as for [`Dice::expected_keep_highest`] apply
+    pub fn expected_keep_lowest(&self, keep: u32) -> f64 {
+        let dropped = self.quantity - keep.min(self.quantity);
+        self.expected_kept(|successes| successes.saturating_sub(dropped))
+    }
+
+    /// Expected sum of the kept dice, computed with order statistics
+    ///
+    /// A die shows at least `x` with probability `p = (s - x + 1) / s`, so the number of dice
+    /// showing at least `x` is binomially distributed. Summing over every face value `x`
+    /// how many of the kept dice are at least `x` gives the kept total,
+    /// and `kept_at_least(m)` says how many kept dice that is when `m` dice rolled at least `x`
+    fn expected_kept(&self, kept_at_least: impl Fn(u32) -> u32) -> f64 {
+        let n = self.quantity;
+        let sides = f64::from(self.num_sides);
+
+        let mut expected = 0.0;
+        for x in 1..=self.num_sides {
+            let p = f64::from(self.num_sides - x + 1) / sides;
+            let q = 1.0 - p;
+            if q == 0.0 {
+                expected += f64::from(kept_at_least(n));
+                continue;
+            }
+
+            // The binomial coefficients overflow for large quantities, so walk outwards from
+            // the most likely count using the ratio between neighbouring terms instead.
+            // The weights are relative to the mode and get normalised at the end
+            let mode = ((f64::from(n) + 1.0) * p) as u32;
+            let mode = mode.min(n);
+            let mut total_weight = 1.0;
+            let mut kept = f64::from(kept_at_least(mode));
+
+            let mut weight = 1.0;
+            for m in (0..mode).rev() {
+                weight *= f64::from(m + 1) / f64::from(n - m) * q / p;
+                if weight == 0.0 {
+                    break;
+                }
+                total_weight += weight;
+                kept += weight * f64::from(kept_at_least(m));
+            }
+
+            let mut weight = 1.0;
+            for m in mode + 1..=n {
+                weight *= f64::from(n - m + 1) / f64::from(m) * p / q;
+                if weight == 0.0 {
+                    break;
+                }
+                total_weight += weight;
+                kept += weight * f64::from(kept_at_least(m));
+            }
+
+            expected += kept / total_weight;
+        }
+
+        expected
+    }
+
+    /// A single four-sided die
+    pub const D4: Dice = Dice::single(4);
+
+    /// A single six-sided die
+    pub const D6: Dice = Dice::single(6);
+
+    /// A single eight-sided die
+    pub const D8: Dice = Dice::single(8);
+
+    /// A single ten-sided die
+    pub const D10: Dice = Dice::single(10);
+
+    /// A single twelve-sided die
+    pub const D12: Dice = Dice::single(12);
+
+    /// A single twenty-sided die
+    pub const D20: Dice = Dice::single(20);
+
+    /// A single percentile die
+    pub const D100: Dice = Dice::single(100);
+
     /// Basic constructor for a new dice value
-    pub fn new(quantity: u32, num_sides: u32) -> Self {
+    pub const fn new(quantity: u32, num_sides: u32) -> Self {
         Self {
             num_sides,
             quantity,
@@ -83,48 +379,336 @@ impl Dice {
     }
 
     /// Convenience function to obtain a single die
-    pub fn single(num_sides: u32) -> Self {
+    pub const fn single(num_sides: u32) -> Self {
         Self::new(1, num_sides)
     }
 }
 
+/// Dice on a ladder of die types, as used in Savage Worlds
+///
+/// Going past the end of the ladder adds a flat modifier instead,
+/// so a d12 stepped up is a d12 + 1, and a d4 stepped down is a d4 - 1
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct DieStep {
+    /// The dice being rolled
+    pub dice: Dice,
+
+    /// The flat modifier added to the roll
+    pub modifier: i32,
+}
+
+impl DieStep {
+    /// The number of sides on every step of the ladder, from the lowest
+    pub const LADDER: [u32; 5] = [4, 6, 8, 10, 12];
+
+    /// Basic constructor for dice without a modifier
+    pub const fn new(dice: Dice) -> Self {
+        Self { dice, modifier: 0 }
+    }
+
+    /// Move one step up
+    ///
+    /// Dice move to the next larger die on the ladder, even when they aren't on it,
+    /// so a d7 becomes a d8, and keep their modifier, so a d6 + 2 becomes a d8 + 2.
+    /// A negative modifier is paid off first, and without a larger die
+    /// the modifier grows by one instead
+    pub fn step_up(&self) -> Self {
+        let larger = Self::LADDER
+            .into_iter()
+            .find(|&sides| sides > self.dice.num_sides);
+        match larger {
+            Some(sides) if self.modifier >= 0 => self.with_sides(sides),
+            _ => Self {
+                modifier: self.modifier.saturating_add(1),
+                ..*self
+            },
+        }
+    }
+
+    /// Move one step down
+    ///
+    /// Works like [`DieStep::step_up`] in reverse: a positive modifier is taken away first,
+    /// otherwise the dice move to the next smaller die with their modifier,
+    /// and below a d4 the modifier shrinks by one
+    pub fn step_down(&self) -> Self {
+        let smaller = Self::LADDER
+            .into_iter()
+            .rev()
+            .find(|&sides| sides < self.dice.num_sides);
+        match smaller {
+            Some(sides) if self.modifier <= 0 => self.with_sides(sides),
+            _ => Self {
+                modifier: self.modifier.saturating_sub(1),
+                ..*self
+            },
+        }
+    }
+
+    fn with_sides(&self, num_sides: u32) -> Self {
+        Self {
+            dice: Dice::new(self.dice.quantity, num_sides),
+            ..*self
+        }
+    }
+}
+
+impl Display for DieStep {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.modifier {
+            0 => write!(f, "{}", self.dice),
+            m if m > 0 => write!(f, "{} + {m}", self.dice),
+            m => write!(f, "{} - {}", self.dice, m.unsigned_abs()),
+        }
+    }
+}
+
+/// Whether a d20 check is made with advantage, disadvantage or neither
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum Advantage {
+    /// Roll a single d20
+    #[default]
+    Normal,
+
+    /// Roll two d20s and keep the higher one
+    Advantage,
+
+    /// Roll two d20s and keep the lower one
+    Disadvantage,
+}
+
+/// Thresholds for the special outcomes of a roll-under check
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RollUnderThresholds {
+    /// A roll at or below `target / critical_divisor` is a critical
+    pub critical_divisor: u32,
+
+    /// A roll at or above this value is a fumble
+    pub fumble_from: u64,
+}
+
+impl Default for RollUnderThresholds {
+    /// The Call of Cthulhu thresholds: a fifth of the skill is a critical, 96-100 is a fumble
+    fn default() -> Self {
+        Self {
+            critical_divisor: 5,
+            fumble_from: 96,
+        }
+    }
+}
+
+/// Outcome of a roll-under check
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum RollUnderResult {
+    /// The roll was far enough below the target
+    Critical,
+
+    /// The roll was at or below the target
+    Success,
+
+    /// The roll was above the target
+    Failure,
+
+    /// The roll was high enough to go horribly wrong
+    Fumble,
+}
+
+impl RollUnderResult {
+    /// Classify the total of a roll-under check
+    ///
+    /// A fumble is checked first, so it takes precedence even over a roll under the target
+    pub fn new(roll: u64, target: u32, thresholds: RollUnderThresholds) -> Self {
+        let critical = target
+            .checked_div(thresholds.critical_divisor)
+            .unwrap_or_default();
+        if roll >= thresholds.fumble_from {
+            RollUnderResult::Fumble
+        } else if roll <= u64::from(critical) {
+            RollUnderResult::Critical
+        } else if roll <= u64::from(target) {
+            RollUnderResult::Success
+        } else {
+            RollUnderResult::Failure
+        }
+    }
+
+    /// Whether the check passed, critically or not
+    pub fn is_success(&self) -> bool {
+        matches!(self, RollUnderResult::Critical | RollUnderResult::Success)
+    }
+}
+
+/// Outcome of a dice pool that counts successes and subtracts botches
+///
+/// As used in World of Darkness, where every die at or above the target is a success
+/// and every die at or below the botch value cancels one out
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PoolResult {
+    /// Number of dice at or above the target
+    pub successes: u32,
+
+    /// Number of dice at or below the botch value
+    pub botches: u32,
+
+    /// Successes minus botches, negative when the botches win
+    pub net: i64,
+}
+
+impl PoolResult {
+    /// Count the successes and botches in a roll
+    ///
+    /// The two are counted independently,
+    /// so a die can only be both if `botch` is not below `target`
+    pub fn new(roll: &[u32], target: u32, botch: u32) -> Self {
+        let successes = roll.iter().filter(|&&res| res >= target).count() as u32;
+        let botches = roll.iter().filter(|&&res| res <= botch).count() as u32;
+
+        Self {
+            successes,
+            botches,
+            net: i64::from(successes) - i64::from(botches),
+        }
+    }
+}
+
+/// Sum up the results of several rolls
+///
+/// Handy when a few dice are rolled separately but only the grand total matters
+///
+/// ```rust
+/// # use rusty_dice::{Dice, total};
+/// let mut rng = rand::rng();
+/// let rolls = [Dice::new(2, 6).roll_with(&mut rng), Dice::D8.roll_with(&mut rng)];
+/// assert!((3..=20).contains(&total(&rolls)));
+/// ```
+pub fn total(rolls: &[Vec<u32>]) -> u64 {
+    rolls.iter().flatten().map(|&res| u64::from(res)).sum()
+}
+
 impl FromStr for Dice {
     type Err = DiceError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let dice_parts = s.split("d").collect::<Vec<_>>();
 
-        let to_err = || DiceError::InvalidExpression(s.to_string());
+        let to_err = |e: ParseIntError| match e.kind() {
+            IntErrorKind::PosOverflow => DiceError::Overflow(s.to_string()),
+            _ => DiceError::InvalidExpression(s.to_string()),
+        };
 
         if dice_parts.len() != 2 {
-            return Err(to_err());
+            return Err(DiceError::InvalidExpression(s.to_string()));
         }
 
         let quantity = dice_parts
             .first()
             .expect("There should always be the first element in the dice label")
             .parse::<u32>()
-            .map_err(|_| to_err())?;
+            .map_err(to_err)?;
 
         let num_sides = dice_parts
             .get(1)
             .expect("There should always be the second element in the dice label")
             .parse::<u32>()
-            .map_err(|_| to_err())?;
+            .map_err(to_err)?;
 
         Ok(Dice::new(quantity, num_sides))
     }
 }
 
+impl TryFrom<&str> for Dice {
+    type Error = DiceError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TryFrom<String> for Dice {
+    type Error = DiceError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
 impl Display for Dice {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}d{}", self.quantity, self.num_sides)
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+/// One or more dice of the same type, with arbitrary values on their faces
+///
+/// Every face has an equal chance of appearing, so a value listed
+/// several times is proportionally more likely to come up
+pub struct CustomDice {
+    /// The number of dice represented by this value
+    pub quantity: u32,
+
+    /// The values on the faces of every die
+    pub faces: Vec<i32>,
+}
+
+impl CustomDice {
+    /// Basic constructor for a new custom dice value
+    pub fn new(quantity: u32, faces: Vec<i32>) -> Self {
+        Self { quantity, faces }
+    }
+
+    /// Convenience function to obtain averaging dice
+    ///
+    /// An averaging die is a six-sided die with faces 2, 3, 3, 4, 4, 5, as used in Warhammer
+    pub fn averaging(quantity: u32) -> Self {
+        Self::new(quantity, vec![2, 3, 3, 4, 4, 5])
+    }
+
+    /// Method for rolling the dice and obtaining the values
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dice have no faces
+    #[cfg(feature = "std")]
+    pub fn roll(&self) -> Vec<i32> {
+        self.roll_with(&mut rand::rng())
+    }
+
+    /// Method for rolling the dice with the provided random number generator
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dice have no faces
+    pub fn roll_with<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<i32> {
+        self.roll_iter_with(rng).collect()
+    }
+
+    /// Method for rolling the dice one die at a time
+    ///
+    /// See [`Dice::roll_iter_with`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dice have no faces
+    pub fn roll_iter_with<R: Rng + ?Sized>(&self, rng: &mut R) -> impl Iterator<Item = i32> {
+        (1..=self.quantity).map(move |_| self.faces[rng.random_range(0..self.faces.len())])
+    }
+}
+
+impl Display for CustomDice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let faces = self
+            .faces
+            .iter()
+            .map(|face| face.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}d[{}]", self.quantity, faces.join(","))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
 
     #[test]
     fn to_string() {
@@ -151,4 +735,390 @@ mod tests {
             assert_eq!(res, Err(DiceError::InvalidExpression(test)));
         }
     }
+
+    #[test]
+    fn parse_overflow() {
+        for test in ["4294967296d6", "2d99999999999"] {
+            let res = test.parse::<Dice>();
+            assert_eq!(res, Err(DiceError::Overflow(test.to_string())));
+        }
+
+        assert_eq!(
+            "4294967295d4294967295".parse(),
+            Ok(Dice::new(u32::MAX, u32::MAX))
+        );
+        assert_eq!(
+            "2dlots".parse::<Dice>(),
+            Err(DiceError::InvalidExpression("2dlots".to_string()))
+        );
+    }
+
+    #[test]
+    fn clone_error() {
+        let err = "3d".parse::<Dice>().unwrap_err();
+        let copy = err.clone();
+        assert_eq!(err, copy);
+        assert_eq!(copy.to_string(), "Failed to parse dice expression: `3d`");
+    }
+
+    #[test]
+    fn try_from() {
+        assert_eq!(Dice::try_from("3d6"), Ok(Dice::new(3, 6)));
+        assert_eq!(Dice::try_from("2d10".to_string()), Ok(Dice::new(2, 10)));
+
+        assert_eq!(
+            Dice::try_from("3d"),
+            Err(DiceError::InvalidExpression("3d".to_string()))
+        );
+        assert_eq!(
+            Dice::try_from("d20d".to_string()),
+            Err(DiceError::InvalidExpression("d20d".to_string()))
+        );
+    }
+
+    #[test]
+    fn const_dice() {
+        const STANDARD: [Dice; 7] = [
+            Dice::D4,
+            Dice::D6,
+            Dice::D8,
+            Dice::D10,
+            Dice::D12,
+            Dice::D20,
+            Dice::D100,
+        ];
+        const FIREBALL: Dice = Dice::new(8, 6);
+
+        let repr = STANDARD.map(|d| d.to_string());
+        assert_eq!(repr, ["1d4", "1d6", "1d8", "1d10", "1d12", "1d20", "1d100"]);
+        assert_eq!(FIREBALL, "8d6".parse().unwrap());
+    }
+
+    #[test]
+    fn total_of_rolls() {
+        assert_eq!(total(&[vec![3, 5], vec![7], vec![]]), 15);
+        assert_eq!(
+            total(&[vec![u32::MAX], vec![u32::MAX]]),
+            2 * u64::from(u32::MAX)
+        );
+        assert_eq!(total(&[]), 0);
+    }
+
+    #[test]
+    fn roll_until() {
+        let natural_20 = |roll: &[u32]| roll == [20];
+        let (attempts, success) =
+            Dice::D20.roll_until(&mut StdRng::seed_from_u64(42), natural_20, 1000);
+        assert!(success);
+        assert_eq!(attempts.last().unwrap(), &[20]);
+        assert!(
+            attempts[..attempts.len() - 1]
+                .iter()
+                .all(|roll| roll != &[20])
+        );
+
+        let (attempts, success) = Dice::D6.roll_until(&mut StdRng::seed_from_u64(42), |_| false, 5);
+        assert!(!success);
+        assert_eq!(attempts.len(), 5);
+    }
+
+    #[test]
+    fn roll_under() {
+        let rolled = Dice::D100.roll_with(&mut StdRng::seed_from_u64(42))[0];
+
+        let check = |target| Dice::D100.roll_under(&mut StdRng::seed_from_u64(42), target);
+        assert!(check(rolled));
+        assert!(check(rolled + 1));
+        assert!(!check(rolled - 1));
+
+        let mut rng = StdRng::seed_from_u64(42);
+        assert!((0..100).all(|_| Dice::D100.roll_under(&mut rng, 100)));
+        assert!((0..100).all(|_| !Dice::D100.roll_under(&mut rng, 0)));
+    }
+
+    #[test]
+    fn roll_under_detailed() {
+        let thresholds = RollUnderThresholds::default();
+        let classify = |roll| RollUnderResult::new(roll, 55, thresholds);
+
+        assert_eq!(classify(1), RollUnderResult::Critical);
+        assert_eq!(classify(11), RollUnderResult::Critical);
+        assert_eq!(classify(12), RollUnderResult::Success);
+        assert_eq!(classify(55), RollUnderResult::Success);
+        assert_eq!(classify(56), RollUnderResult::Failure);
+        assert_eq!(classify(95), RollUnderResult::Failure);
+        assert_eq!(classify(96), RollUnderResult::Fumble);
+        assert_eq!(classify(100), RollUnderResult::Fumble);
+
+        let strict = RollUnderThresholds {
+            critical_divisor: 0,
+            fumble_from: 100,
+        };
+        assert_eq!(
+            RollUnderResult::new(1, 55, strict),
+            RollUnderResult::Success
+        );
+        assert_eq!(
+            RollUnderResult::new(99, 100, strict),
+            RollUnderResult::Success
+        );
+
+        let rolled = Dice::D100.roll_with(&mut StdRng::seed_from_u64(42))[0];
+        let res = Dice::D100.roll_under_detailed(&mut StdRng::seed_from_u64(42), 55, thresholds);
+        assert_eq!(res, classify(u64::from(rolled)));
+        assert_eq!(res.is_success(), rolled <= 55);
+    }
+
+    #[test]
+    fn d20_check() {
+        let seeded = || StdRng::seed_from_u64(42);
+        let pair = Dice::new(2, 20).roll_with(&mut seeded());
+
+        assert_eq!(
+            Dice::d20_check(&mut seeded(), Advantage::Normal),
+            Dice::D20.roll_with(&mut seeded())[0]
+        );
+        assert_eq!(
+            Dice::d20_check(&mut seeded(), Advantage::Advantage),
+            pair[0].max(pair[1])
+        );
+        assert_eq!(
+            Dice::d20_check(&mut seeded(), Advantage::Disadvantage),
+            pair[0].min(pair[1])
+        );
+    }
+
+    #[test]
+    fn mode() {
+        assert_eq!(Dice::new(2, 6).mode(), [7]);
+        assert_eq!(Dice::new(3, 6).mode(), [10, 11]);
+        assert_eq!(Dice::D6.mode(), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(Dice::new(4, 1).mode(), [4]);
+        assert_eq!(Dice::new(0, 6).mode(), [0]);
+        assert_eq!(Dice::new(2, u32::MAX).mode(), [u64::from(u32::MAX) + 1]);
+    }
+
+    #[test]
+    fn die_steps() {
+        assert_eq!(Dice::D6.step_up(), DieStep::new(Dice::D8));
+        assert_eq!(Dice::D6.step_down(), DieStep::new(Dice::D4));
+        assert_eq!(Dice::new(2, 7).step_up(), DieStep::new(Dice::new(2, 8)));
+
+        let d12_up = Dice::D12.step_up();
+        assert_eq!((d12_up.dice, d12_up.modifier), (Dice::D12, 1));
+        assert_eq!(d12_up.step_up().to_string(), "1d12 + 2");
+        assert_eq!(d12_up.step_down(), DieStep::new(Dice::D12));
+
+        let d4_down = Dice::D4.step_down();
+        assert_eq!((d4_down.dice, d4_down.modifier), (Dice::D4, -1));
+        assert_eq!(d4_down.to_string(), "1d4 - 1");
+        assert_eq!(d4_down.step_up(), DieStep::new(Dice::D4));
+
+        let climbed = (0..4).fold(DieStep::new(Dice::D4), |step, _| step.step_up());
+        assert_eq!(climbed, DieStep::new(Dice::D12));
+
+        // A positive modifier rides along while there are larger dice
+        let boosted = DieStep {
+            dice: Dice::D6,
+            modifier: 2,
+        };
+        assert_eq!(boosted.step_up().to_string(), "1d8 + 2");
+        assert_eq!(boosted.step_down().to_string(), "1d6 + 1");
+
+        // And a negative one while there are smaller dice
+        let weakened = DieStep {
+            dice: Dice::D8,
+            modifier: -1,
+        };
+        assert_eq!(weakened.step_down().to_string(), "1d6 - 1");
+        assert_eq!(weakened.step_up(), DieStep::new(Dice::D8));
+
+        let maxed = DieStep {
+            dice: Dice::D12,
+            modifier: i32::MAX,
+        };
+        assert_eq!(maxed.step_up(), maxed);
+        let bottomed = DieStep {
+            dice: Dice::D4,
+            modifier: i32::MIN,
+        };
+        assert_eq!(bottomed.step_down(), bottomed);
+    }
+
+    #[test]
+    fn expected_keep() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+        // 4d6 drop lowest, the exact value is 15869 / 1296
+        assert!(close(
+            Dice::new(4, 6).expected_keep_highest(3),
+            15869.0 / 1296.0
+        ));
+
+        // Advantage and disadvantage on a d20
+        assert!(close(Dice::new(2, 20).expected_keep_highest(1), 13.825));
+        assert!(close(Dice::new(2, 20).expected_keep_lowest(1), 7.175));
+
+        // Keeping everything is just the regular mean
+        assert!(close(Dice::new(3, 8).expected_keep_highest(3), 13.5));
+        assert!(close(Dice::new(3, 8).expected_keep_lowest(10), 13.5));
+        assert!(close(Dice::new(3, 8).expected_keep_highest(0), 0.0));
+
+        // Large pools don't overflow, and almost surely have three sixes and three ones
+        for quantity in [1100, 2000, 5000] {
+            let dice = Dice::new(quantity, 6);
+            let highest = dice.expected_keep_highest(3);
+            assert!(highest.is_finite());
+            assert!(close(highest, 18.0));
+            assert!(close(dice.expected_keep_lowest(3), 3.0));
+
+            let all = dice.expected_keep_highest(quantity);
+            assert!((all - f64::from(quantity) * 3.5).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn pool_result() {
+        let res = PoolResult::new(&[1, 3, 8, 10, 9, 1], 8, 1);
+        assert_eq!(
+            res,
+            PoolResult {
+                successes: 3,
+                botches: 2,
+                net: 1
+            }
+        );
+
+        let res = PoolResult::new(&[1, 1, 8], 8, 1);
+        assert_eq!((res.successes, res.botches, res.net), (1, 2, -1));
+        assert_eq!(PoolResult::new(&[], 8, 1).net, 0);
+    }
+
+    #[test]
+    fn roll_n() {
+        let dice = Dice::new(3, 6);
+        let rolls = dice.roll_n(&mut StdRng::seed_from_u64(42), 100);
+        assert_eq!(rolls.len(), 100);
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let expected = (0..100)
+            .map(|_| dice.roll_with(&mut rng))
+            .collect::<Vec<_>>();
+        assert_eq!(rolls, expected);
+    }
+
+    #[test]
+    fn rolls() {
+        let dice = Dice::new(2, 8);
+        let rolls = dice
+            .rolls(&mut StdRng::seed_from_u64(1))
+            .take(5)
+            .collect::<Vec<_>>();
+
+        assert_eq!(rolls.len(), 5);
+        for roll in rolls {
+            assert_eq!(roll.len(), 2);
+            assert!(roll.iter().all(|r| (1..=8).contains(r)));
+        }
+    }
+
+    #[test]
+    fn roll_concat() {
+        let d66 = Dice::new(2, 6);
+        let mut rng = StdRng::seed_from_u64(66);
+        for _ in 0..1000 {
+            let res = d66.roll_concat_with(&mut rng);
+            assert!((1..=6).contains(&(res / 10)), "res = {res}");
+            assert!((1..=6).contains(&(res % 10)), "res = {res}");
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_large_quantity() {
+        let rolls = Dice::new(100_000, 6).roll();
+        assert_eq!(rolls.len(), 100_000);
+        assert!(rolls.iter().all(|r| (1..=6).contains(r)));
+    }
+
+    #[test]
+    fn roll_extreme_sides() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let rolls = Dice::single(u32::MAX).roll_with(&mut rng);
+        assert_eq!(rolls.len(), 1);
+        assert!(rolls[0] >= 1);
+
+        let rolls = Dice::new(1000, u32::MAX).roll_with(&mut rng);
+        assert!(total(&[rolls]) > u64::from(u32::MAX));
+
+        assert!(Dice::new(0, 0).roll_with(&mut rng).is_empty());
+    }
+
+    #[test]
+    fn roll_extreme_quantity() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let largest = Dice::new(u32::MAX, u32::MAX);
+
+        // Every die is rolled lazily, so this takes no memory until it is iterated
+        let mut rolls = largest.roll_iter_with(&mut rng);
+        assert_eq!(rolls.size_hint().1, usize::try_from(u32::MAX).ok());
+        assert!(rolls.by_ref().take(1000).all(|r| r >= 1));
+
+        // The largest possible total still fits into the `u64` that `total` returns
+        let max_total = u64::from(largest.quantity).checked_mul(u64::from(largest.num_sides));
+        assert!(max_total.is_some());
+    }
+
+    #[test]
+    fn roll_iter() {
+        let dice = Dice::new(20, 6);
+        let lazy = dice
+            .roll_iter_with(&mut StdRng::seed_from_u64(8))
+            .collect::<Vec<_>>();
+        assert_eq!(lazy, dice.roll_with(&mut StdRng::seed_from_u64(8)));
+
+        let mut rng = StdRng::seed_from_u64(8);
+        let many = Dice::new(u32::MAX, 6);
+        let mut rolls = many.roll_iter_with(&mut rng);
+        assert!(rolls.by_ref().take(1000).all(|r| (1..=6).contains(&r)));
+        assert!(rolls.next().is_some());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn roll_secure() {
+        let rolls = Dice::new(50, 20).roll_secure();
+        assert_eq!(rolls.len(), 50);
+        assert!(rolls.iter().all(|r| (1..=20).contains(r)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn hash() {
+        let set =
+            std::collections::HashSet::from([Dice::single(6), Dice::new(1, 6), Dice::new(2, 6)]);
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&Dice::new(2, 6)));
+    }
+
+    #[test]
+    fn custom_dice() {
+        let fudge = CustomDice::new(4, vec![-1, -1, 0, 0, 1, 1]);
+        assert_eq!(fudge.to_string(), "4d[-1,-1,0,0,1,1]");
+
+        let rolls = fudge.roll_with(&mut StdRng::seed_from_u64(9));
+        assert_eq!(rolls.len(), 4);
+        assert!(rolls.iter().all(|r| fudge.faces.contains(r)));
+    }
+
+    #[test]
+    fn averaging_dice() {
+        let rolls = CustomDice::averaging(60_000).roll_with(&mut StdRng::seed_from_u64(3));
+
+        let count = |face| rolls.iter().filter(|r| **r == face).count();
+        assert_eq!(count(2) + count(3) + count(4) + count(5), rolls.len());
+        for (face, expected) in [(2, 10_000), (3, 20_000), (4, 20_000), (5, 10_000)] {
+            assert!(count(face).abs_diff(expected) < 1_000, "face {face}");
+        }
+    }
 }
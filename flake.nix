@@ -12,6 +12,22 @@
         naersk-lib = pkgs.callPackage naersk { };
       in {
         defaultPackage = naersk-lib.buildPackage ./.;
+        checks.rusty-dice-no-std = naersk-lib.buildPackage {
+          src = ./.;
+          cargoBuildOptions = opts:
+            opts ++ [ "-p" "rusty-dice" "--no-default-features" ];
+          # Also run the tests, so they keep compiling without `std`
+          doCheck = true;
+          cargoTestOptions = opts:
+            opts ++ [ "-p" "rusty-dice" "--no-default-features" ];
+        };
+        checks.rusty-dice-expressions-wasm = naersk-lib.buildPackage {
+          src = ./.;
+          mode = "check";
+          CARGO_BUILD_TARGET = "wasm32-unknown-unknown";
+          cargoBuildOptions = opts:
+            opts ++ [ "-p" "rusty-dice-expressions" "--features" "wasm" ];
+        };
         devShell = with pkgs;
           mkShell {
             buildInputs =
@@ -1,29 +1,159 @@
-use anyhow::Result;
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{bail, Context, Result};
 use clap::Parser;
 use log::debug;
 
 use crate::cli::CliArgs;
-use rusty_dice_expressions::{eval::Eval, parse::ExprKind};
+use rusty_dice_expressions::{
+    eval::Eval,
+    parse::{Atom, Expr, ExprKind},
+    ExpressionError, RollRegistry,
+};
 
 #[derive(Debug, Clone)]
 pub struct App {
     args: CliArgs,
 }
 
-fn format_expr(expr: &ExprKind) -> Result<String> {
-    let res = match expr {
-        ExprKind::Simple(expr) => format!("{}: {}", expr, expr.clone().eval()?),
-        ExprKind::Labeled(l, expr) => format!("{l}: {}", expr.clone().eval()?),
-        ExprKind::Separated(expr_kinds) => expr_kinds
-            .iter()
-            .map(|e| format_expr(e))
-            .collect::<Result<Vec<_>, _>>()?
-            .join("\n"),
+fn group_digits(num: i32) -> String {
+    let digits = num.unsigned_abs().to_string();
+    let mut res = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            res.push(',');
+        }
+        res.push(c);
+    }
+
+    if num < 0 {
+        format!("-{res}")
+    } else {
+        res
+    }
+}
+
+fn roll_total(expr: &Expr, args: &CliArgs) -> Result<Expr> {
+    let res = match args.best_of {
+        Some(n) => Expr::Constant(Atom::Number(
+            ExprKind::Simple(expr.clone()).best_of(&mut rand::rng(), n)?,
+        )),
+        None => expr.clone().eval()?,
+    };
+
+    // Floored before anything can reference it, so references see the printed total
+    let res = match (res.get_num(), args.floor) {
+        (Some(num), Some(floor)) => Expr::Constant(Atom::Number(num.max(floor))),
+        _ => res,
     };
 
     Ok(res)
 }
 
+fn format_total(res: &Expr, args: &CliArgs) -> String {
+    match res.get_num() {
+        Some(num) if args.group_digits => group_digits(num),
+        _ => res.to_string(),
+    }
+}
+
+/// Format a single part of an expression, resolving references to the parts before it
+fn format_part(
+    expr: &ExprKind,
+    args: &CliArgs,
+    values: &mut HashMap<String, i32>,
+) -> Result<String> {
+    let sep = &args.separator;
+    let (label, formula) = match expr {
+        ExprKind::Simple(expr) => (None, expr),
+        ExprKind::Labeled(l, expr) => (Some(l), expr),
+        ExprKind::Separated(_) => return format_expr(expr, args),
+    };
+
+    let res = roll_total(&formula.clone().resolve(values), args)?;
+    if let Some(l) = label {
+        ExprKind::Labeled(l.clone(), res.clone()).record_value(values);
+    }
+
+    let total = format_total(&res, args);
+    let res = match label {
+        _ if args.quiet => total,
+        None => format!("{formula}{sep}{total}"),
+        Some(l) if args.formula => format!("{l} ({formula}){sep}{total}"),
+        Some(l) => format!("{l}{sep}{total}"),
+    };
+
+    Ok(res)
+}
+
+fn format_expr(expr: &ExprKind, args: &CliArgs) -> Result<String> {
+    let mut values = HashMap::new();
+    let res = expr
+        .iter()
+        .map(|e| format_part(e, args, &mut values))
+        .collect::<Result<Vec<_>, _>>()?
+        .join(&args.delimiter);
+
+    Ok(res)
+}
+
+fn parse_macros(src: &str) -> Result<Vec<ExprKind>> {
+    let mut res = vec![];
+    for (n, line) in src.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let expr = line
+            .parse::<ExprKind>()
+            .with_context(|| format!("invalid roll on line {}", n + 1))?;
+        res.extend(expr.iter().cloned());
+    }
+
+    Ok(res)
+}
+
+fn read_macros(path: &Path, names: &[String]) -> Result<Vec<ExprKind>> {
+    let src = fs::read_to_string(path)
+        .with_context(|| format!("could not read roll file {}", path.display()))?;
+    let macros = parse_macros(&src)?;
+
+    if names.is_empty() {
+        return Ok(vec![ExprKind::Separated(macros)]);
+    }
+
+    let mut registry = RollRegistry::new();
+    for roll in &macros {
+        registry.register_labeled(roll);
+    }
+
+    names
+        .iter()
+        .map(|name| {
+            registry
+                .get(name.trim())
+                .cloned()
+                .with_context(|| format!("no roll named `{name}` in {}", path.display()))
+        })
+        .collect()
+}
+
+fn read_expressions(args: &CliArgs) -> Result<Vec<ExprKind>> {
+    if let Some(path) = &args.file {
+        return read_macros(path, &args.expression);
+    }
+
+    args.expression
+        .iter()
+        .map(|expression| match expression.parse::<ExprKind>() {
+            Err(ExpressionError::EmptyExpression) => {
+                bail!("nothing to roll, try something like `roll-cli \"1d20 + 5\"`")
+            }
+            res => Ok(res?),
+        })
+        .collect()
+}
+
 impl App {
     pub fn new() -> Self {
         let res = Self {
@@ -36,10 +166,133 @@ impl App {
     }
 
     pub fn run(&self) -> Result<()> {
-        let expr = self.args.expression.parse::<ExprKind>()?;
-        debug!("Parsed expression: {:#?}", expr);
-        println!("{}", format_expr(&expr)?);
+        let exprs = read_expressions(&self.args)?;
+        debug!("Parsed expressions: {:#?}", exprs);
+        for expr in &exprs {
+            println!("{}", format_expr(expr, &self.args)?);
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_from_args(args: &[&str]) -> String {
+        let args = CliArgs::parse_from(std::iter::once("roll-cli").chain(args.iter().copied()));
+        read_expressions(&args)
+            .unwrap()
+            .iter()
+            .map(|expr| format_expr(expr, &args).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_default_format() {
+        assert_eq!(format_from_args(&["str: 3; dex: 5"]), "str: 3\ndex: 5");
+    }
+
+    #[test]
+    fn test_multiple_expressions() {
+        assert_eq!(format_from_args(&["3 + 1"]), "3 + 1: 4");
+        assert_eq!(
+            format_from_args(&["3 + 1", "str: 3; dex: 5"]),
+            "3 + 1: 4\nstr: 3\ndex: 5"
+        );
+
+        let args = CliArgs::parse_from(["roll-cli", "1d6", "  "]);
+        assert!(read_expressions(&args).is_err());
+    }
+
+    #[test]
+    fn test_custom_separators() {
+        let res = format_from_args(&["--separator", "=", "--delimiter", "\t", "str: 3; dex: 5"]);
+        assert_eq!(res, "str=3\tdex=5");
+    }
+
+    #[test]
+    fn test_quiet() {
+        assert_eq!(format_from_args(&["-q", "3 + 1"]), "4");
+        assert_eq!(format_from_args(&["--quiet", "str: 3; 1d1"]), "3\n1");
+
+        let res = format_from_args(&["-q", "2d6"]).parse::<i32>().unwrap();
+        assert!((2..=12).contains(&res));
+    }
+
+    #[test]
+    fn test_references() {
+        assert_eq!(
+            format_from_args(&["atk: 3 + 4; dmg: 2; atk + dmg"]),
+            "atk: 7\ndmg: 2\natk + dmg: 9"
+        );
+        assert_eq!(
+            format_from_args(&["-q", "--floor", "0", "a: 1 - 5; a + 10"]),
+            "0\n10"
+        );
+    }
+
+    #[test]
+    fn test_formula() {
+        assert_eq!(
+            format_from_args(&["--formula", "bonus: 2 + 3; 4"]),
+            "bonus (2 + 3): 5\n4: 4"
+        );
+        assert_eq!(format_from_args(&["bonus: 2 + 3"]), "bonus: 5");
+    }
+
+    #[test]
+    fn test_group_digits() {
+        assert_eq!(group_digits(0), "0");
+        assert_eq!(group_digits(999), "999");
+        assert_eq!(group_digits(3521), "3,521");
+        assert_eq!(group_digits(1234567), "1,234,567");
+        assert_eq!(group_digits(-100000), "-100,000");
+        assert_eq!(
+            format_from_args(&["-q", "--group-digits", "2500 + 1021"]),
+            "3,521"
+        );
+    }
+
+    #[test]
+    fn test_macro_file() {
+        let path = std::env::temp_dir().join(format!("roll-cli-macros-{}", std::process::id()));
+        fs::write(&path, "fireball: 8d6; heal: 2d4+2\n\nbonus: 2 + 3\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        let exprs = read_macros(Path::new(path), &[]).unwrap();
+        assert_eq!(exprs[0].iter().count(), 3);
+
+        assert_eq!(format_from_args(&["--file", path, "bonus"]), "bonus: 5");
+        let res = format_from_args(&["-q", "--file", path, "heal"]);
+        assert!((4..=10).contains(&res.parse::<i32>().unwrap()));
+
+        assert_eq!(
+            format_from_args(&["--file", path, "bonus", "bonus"]),
+            "bonus: 5\nbonus: 5"
+        );
+        assert!(read_macros(Path::new(path), &["lightning".to_string()]).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_floor() {
+        assert_eq!(format_from_args(&["-q", "--floor", "0", "1d6 - 10"]), "0");
+        assert_eq!(format_from_args(&["-q", "--floor", "-5", "3 - 10"]), "-5");
+        assert_eq!(format_from_args(&["-q", "--floor", "1", "2 + 3"]), "5");
+    }
+
+    #[test]
+    fn test_best_of() {
+        assert_eq!(format_from_args(&["--best-of", "3", "x: 2 + 3"]), "x: 5");
+        for _ in 0..20 {
+            let res = format_from_args(&["-q", "--best-of", "3", "1d6"]);
+            assert!((1..=6).contains(&res.parse::<i32>().unwrap()));
+        }
+
+        let args = ["roll-cli", "--best-of", "0", "1d6"];
+        assert!(CliArgs::try_parse_from(args).is_err());
+    }
+}
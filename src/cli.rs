@@ -1,6 +1,43 @@
+use std::path::PathBuf;
+
 use clap::Parser;
 
 #[derive(Debug, Clone, Parser)]
 pub struct CliArgs {
-    pub expression: String,
+    /// The expressions to roll, or the names of rolls when used with --file
+    #[arg(required_unless_present = "file")]
+    pub expression: Vec<String>,
+
+    /// File of named rolls, one or more `name: expression` entries per line
+    #[arg(short, long)]
+    pub file: Option<PathBuf>,
+
+    /// Text placed between a label and its result
+    #[arg(long, default_value = ": ")]
+    pub separator: String,
+
+    /// Text placed between the results of separated expressions
+    #[arg(long, default_value = "\n")]
+    pub delimiter: String,
+
+    /// Print only the rolled totals, without labels or formulas
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Also print the formula of labeled rolls, e.g. `attack (1d20 + 5): 17`
+    #[arg(long, conflicts_with = "quiet")]
+    pub formula: bool,
+
+    /// Group the digits of large totals with commas, e.g. 3,521
+    #[arg(long)]
+    pub group_digits: bool,
+
+    /// Lowest total to report, e.g. 0 so that damage can't be negative.
+    /// Rolls that reference a label use its floored total
+    #[arg(long, allow_negative_numbers = true)]
+    pub floor: Option<i32>,
+
+    /// Roll every expression N times and report the highest total
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub best_of: Option<u32>,
 }